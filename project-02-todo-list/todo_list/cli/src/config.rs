@@ -0,0 +1,61 @@
+//! Reads the RPC URL and keypair path from the Solana CLI config.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::Result;
+
+// used when there is no Solana CLI config, matching a local validator
+const DEFAULT_RPC_URL: &str = "http://localhost:8899";
+
+#[derive(Deserialize, Default)]
+struct CliConfigFile {
+    json_rpc_url: Option<String>,
+    keypair_path: Option<String>,
+}
+
+pub struct Config {
+    pub rpc_url: String,
+    pub keypair_path: PathBuf,
+}
+
+// default location of the Solana CLI config file
+pub fn default_config_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".config/solana/cli/config.yml"))
+}
+
+// load the config file (if any), letting the flags override its values
+pub fn load(
+    config_path: Option<&Path>,
+    rpc_url: Option<String>,
+    keypair_path: Option<PathBuf>,
+) -> Result<Config> {
+    let file = match config_path
+        .map(Path::to_path_buf)
+        .or_else(default_config_path)
+    {
+        Some(path) if path.exists() => serde_yaml::from_str(&fs::read_to_string(&path)?)?,
+        // an explicitly requested config file must exist
+        Some(path) if config_path.is_some() => {
+            return Err(format!("config file {} not found", path.display()).into())
+        }
+        _ => CliConfigFile::default(),
+    };
+
+    let rpc_url = rpc_url
+        .or(file.json_rpc_url)
+        .unwrap_or_else(|| DEFAULT_RPC_URL.to_string());
+    let keypair_path = match keypair_path.or(file.keypair_path.map(PathBuf::from)) {
+        Some(path) => path,
+        None => dirs::home_dir()
+            .ok_or("cannot find the home directory")?
+            .join(".config/solana/id.json"),
+    };
+
+    Ok(Config {
+        rpc_url,
+        keypair_path,
+    })
+}
@@ -0,0 +1,272 @@
+//! `todo` - manage todo lists of the todo_list program from the command line.
+
+mod config;
+mod rpc;
+
+use std::path::PathBuf;
+
+use anchor_lang::system_program;
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use clap::{Parser, Subcommand};
+use serde_json::json;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+use todo_crypto::{
+    add_item_instruction, add_key_envelope_instruction, decrypt_items,
+    enable_encryption_instruction, ListKey,
+};
+use todo_list::{AttachmentKind, TodoList};
+
+use crate::rpc::RpcClient;
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+#[derive(Parser)]
+#[command(name = "todo", about = "Manage todo lists on a Solana cluster")]
+struct Cli {
+    /// RPC URL, defaults to the one in the Solana CLI config
+    #[arg(long, short = 'u', global = true)]
+    url: Option<String>,
+    /// Keypair of the list owner, defaults to the one in the Solana CLI config
+    #[arg(long, short = 'k', global = true)]
+    keypair: Option<PathBuf>,
+    /// Solana CLI config file, defaults to ~/.config/solana/cli/config.yml
+    #[arg(long, short = 'C', global = true)]
+    config: Option<PathBuf>,
+    /// Print output as JSON
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create a todo list for the keypair
+    Create,
+    /// Add an item to the list
+    Add { description: String },
+    /// Show a todo list, the keypair's own list by default
+    Ls {
+        /// Show the list of another owner
+        #[arg(long)]
+        owner: Option<Pubkey>,
+    },
+    /// Mark an item as completed
+    Done { id: u32 },
+    /// Remove an item from the list
+    Rm { id: u32 },
+    /// Close the (empty) list and reclaim its rent
+    Close,
+    /// Encrypt the descriptions of the (empty) list from now on
+    Encrypt,
+    /// Give a collaborator the key to read an encrypted list
+    Share { collaborator: Pubkey },
+}
+
+// the todo list PDA of an owner
+fn todo_list_address(owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"todo-list", owner.as_ref()], &todo_list::ID).0
+}
+
+fn attachment_kind_name(kind: AttachmentKind) -> &'static str {
+    match kind {
+        AttachmentKind::Sha256 => "sha256",
+        AttachmentKind::IpfsCidV1 => "ipfs-cidv1",
+        AttachmentKind::GitCommit => "git-commit",
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn fetch_list(rpc: &RpcClient, address: &Pubkey) -> Result<TodoList> {
+    let data = rpc
+        .get_account_data(address)?
+        .ok_or_else(|| format!("no todo list at {}", address))?;
+    Ok(TodoList::try_deserialize(&mut data.as_slice())?)
+}
+
+fn print_list(address: &Pubkey, list: &TodoList, as_json: bool) {
+    if as_json {
+        let items: Vec<_> = list
+            .items
+            .iter()
+            .map(|item| {
+                let attachments: Vec<_> = item
+                    .attachments
+                    .iter()
+                    .map(|attachment| {
+                        json!({
+                            "kind": attachment_kind_name(attachment.kind),
+                            "digest": hex(&attachment.digest),
+                        })
+                    })
+                    .collect();
+                json!({
+                    "id": item.id,
+                    "description": item.description,
+                    "completed": item.completed,
+                    "attachments": attachments,
+                })
+            })
+            .collect();
+        let output = json!({
+            "address": address.to_string(),
+            "owner": list.owner.to_string(),
+            "next_id": list.next_id,
+            "encrypted": list.encrypted,
+            "items": items,
+            "stats": {
+                "total_created": list.total_created,
+                "total_completed": list.total_completed,
+                "open_count": list.open_count,
+                "last_activity": list.last_activity,
+            },
+        });
+        println!("{}", output);
+        return;
+    }
+
+    println!("{} (owner {})", address, list.owner);
+    if list.items.is_empty() {
+        println!("  no items");
+    }
+    for item in &list.items {
+        let mark = if item.completed { "x" } else { " " };
+        println!("  [{}] {:>3}  {}", mark, item.id, item.description);
+        for attachment in &item.attachments {
+            println!(
+                "          {} {}",
+                attachment_kind_name(attachment.kind),
+                hex(&attachment.digest)
+            );
+        }
+    }
+    println!(
+        "  {} open, {} created, {} completed",
+        list.open_count, list.total_created, list.total_completed
+    );
+}
+
+// sign an instruction with the owner and send it, printing the signature
+fn send(rpc: &RpcClient, owner: &Keypair, instruction: Instruction, as_json: bool) -> Result<()> {
+    let blockhash = rpc.get_latest_blockhash()?;
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&owner.pubkey()),
+        &[owner],
+        blockhash,
+    );
+    let signature = rpc.send_and_confirm_transaction(&transaction)?;
+    if as_json {
+        println!("{}", json!({ "signature": signature.to_string() }));
+    } else {
+        println!("{}", signature);
+    }
+    Ok(())
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let config = config::load(cli.config.as_deref(), cli.url, cli.keypair)?;
+    let rpc = RpcClient::new(config.rpc_url);
+    let load_owner = || {
+        read_keypair_file(&config.keypair_path).map_err(|err| {
+            format!(
+                "cannot read keypair {}: {}",
+                config.keypair_path.display(),
+                err
+            )
+        })
+    };
+
+    // listing another owner's list does not need a keypair
+    if let Command::Ls { owner: other } = cli.command {
+        let address = match other {
+            Some(other) => todo_list_address(&other),
+            None => todo_list_address(&load_owner()?.pubkey()),
+        };
+        let mut list = fetch_list(&rpc, &address)?;
+        // show plaintext when the keypair holds a key envelope, ciphertext otherwise
+        if list.encrypted {
+            if let Some(items) = load_owner()
+                .ok()
+                .and_then(|keypair| decrypt_items(&list, &keypair).ok())
+            {
+                list.items = items;
+            }
+        }
+        print_list(&address, &list, cli.json);
+        return Ok(());
+    }
+
+    let owner = load_owner()?;
+    let todo_list = todo_list_address(&owner.pubkey());
+
+    // instructions that only touch the owner's list share the same accounts
+    let owner_accounts = |todo_list: Pubkey, owner: Pubkey| {
+        todo_list::accounts::AddItem { todo_list, owner }.to_account_metas(None)
+    };
+
+    let instruction = match cli.command {
+        Command::Ls { .. } => unreachable!("handled above"),
+        Command::Create => Instruction {
+            program_id: todo_list::ID,
+            accounts: todo_list::accounts::CreateList {
+                todo_list,
+                user: owner.pubkey(),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: todo_list::instruction::CreateList {}.data(),
+        },
+        Command::Add { description } => {
+            let list = fetch_list(&rpc, &todo_list)?;
+            if list.encrypted {
+                let key = ListKey::from_list(&list, &owner)?;
+                add_item_instruction(&owner.pubkey(), &key, &description)?
+            } else {
+                Instruction {
+                    program_id: todo_list::ID,
+                    accounts: owner_accounts(todo_list, owner.pubkey()),
+                    data: todo_list::instruction::AddItem { description }.data(),
+                }
+            }
+        }
+        Command::Done { id } => Instruction {
+            program_id: todo_list::ID,
+            accounts: owner_accounts(todo_list, owner.pubkey()),
+            data: todo_list::instruction::CompleteItem { item_id: id }.data(),
+        },
+        Command::Rm { id } => Instruction {
+            program_id: todo_list::ID,
+            accounts: owner_accounts(todo_list, owner.pubkey()),
+            data: todo_list::instruction::DeleteItem { item_id: id }.data(),
+        },
+        Command::Close => Instruction {
+            program_id: todo_list::ID,
+            accounts: todo_list::accounts::CloseList {
+                todo_list,
+                owner: owner.pubkey(),
+            }
+            .to_account_metas(None),
+            data: todo_list::instruction::CloseList {}.data(),
+        },
+        Command::Encrypt => enable_encryption_instruction(&owner.pubkey(), &ListKey::generate())?,
+        Command::Share { collaborator } => {
+            let key = ListKey::from_list(&fetch_list(&rpc, &todo_list)?, &owner)?;
+            add_key_envelope_instruction(&owner.pubkey(), &key, &collaborator)?
+        }
+    };
+    send(&rpc, &owner, instruction, cli.json)
+}
+
+fn main() {
+    if let Err(err) = run(Cli::parse()) {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,355 @@
+//! Client-side encryption of todo item descriptions.
+//!
+//! An encrypted list has a single symmetric [`ListKey`]. Item descriptions
+//! are ChaCha20-Poly1305 ciphertexts under that key, stored on chain as
+//! base64 of `nonce || ciphertext`. The key itself lives on the list as one
+//! [`KeyEnvelope`] per collaborator, wrapped through an x25519 key exchange
+//! with the collaborator's Solana key (converted to its Montgomery form), so
+//! any wallet can be given access from its public key alone.
+
+use std::fmt;
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce};
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256, Sha512};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use todo_list::{KeyEnvelope, TodoItem, TodoList, MAX_ENCRYPTED_DESCRIPTION_LEN};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+const NONCE_LEN: usize = 12;
+// domain separation for the key-encryption key derivation
+const ENVELOPE_CONTEXT: &[u8] = b"todo-list key envelope";
+
+#[derive(Debug)]
+pub enum Error {
+    /// The collaborator's key is not an ed25519 curve point (e.g. a PDA).
+    InvalidPublicKey,
+    /// The list has no key envelope for the keypair.
+    NoEnvelope,
+    /// The encrypted description exceeds `MAX_ENCRYPTED_DESCRIPTION_LEN`.
+    DescriptionTooLong,
+    /// A key or description could not be encrypted.
+    Encrypt,
+    /// A key envelope or description failed to decrypt.
+    Decrypt,
+    /// A description is not valid base64 ciphertext.
+    InvalidCiphertext,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            Error::InvalidPublicKey => "public key is not a valid ed25519 point",
+            Error::NoEnvelope => "the list has no key envelope for this keypair",
+            Error::DescriptionTooLong => "the encrypted description is too long",
+            Error::Encrypt => "encryption failed",
+            Error::Decrypt => "decryption failed",
+            Error::InvalidCiphertext => "the description is not valid ciphertext",
+        };
+        f.write_str(message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Symmetric key encrypting the descriptions of one list.
+pub struct ListKey([u8; 32]);
+
+impl ListKey {
+    pub fn generate() -> Self {
+        let mut key = [0; 32];
+        OsRng.fill_bytes(&mut key);
+        ListKey(key)
+    }
+
+    /// Recover the list key from the keypair's envelope on `list`.
+    pub fn from_list(list: &TodoList, keypair: &Keypair) -> Result<Self> {
+        let collaborator = keypair.pubkey();
+        let envelope = list
+            .key_envelopes
+            .iter()
+            .find(|envelope| envelope.collaborator == collaborator)
+            .ok_or(Error::NoEnvelope)?;
+        Self::unwrap(envelope, keypair)
+    }
+
+    /// Wrap the key for `collaborator`, who can unwrap it with their keypair.
+    pub fn wrap_for(&self, collaborator: &Pubkey) -> Result<KeyEnvelope> {
+        let their_public = x25519_public(collaborator)?;
+        let ephemeral = StaticSecret::random_from_rng(OsRng);
+        let ephemeral_public = PublicKey::from(&ephemeral);
+        let shared = ephemeral.diffie_hellman(&their_public);
+
+        let cipher = envelope_cipher(shared.as_bytes(), &ephemeral_public, &their_public);
+        let nonce = random_nonce();
+        let wrapped = cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &self.0,
+                    aad: collaborator.as_ref(),
+                },
+            )
+            .map_err(|_| Error::Encrypt)?;
+
+        Ok(KeyEnvelope {
+            collaborator: *collaborator,
+            ephemeral_public_key: ephemeral_public.to_bytes(),
+            nonce,
+            wrapped_key: wrapped.try_into().map_err(|_| Error::Encrypt)?,
+        })
+    }
+
+    /// Unwrap an envelope addressed to `keypair`.
+    pub fn unwrap(envelope: &KeyEnvelope, keypair: &Keypair) -> Result<Self> {
+        let secret = x25519_secret(keypair);
+        let our_public = PublicKey::from(&secret);
+        let ephemeral_public = PublicKey::from(envelope.ephemeral_public_key);
+        let shared = secret.diffie_hellman(&ephemeral_public);
+
+        let cipher = envelope_cipher(shared.as_bytes(), &ephemeral_public, &our_public);
+        let key = cipher
+            .decrypt(
+                Nonce::from_slice(&envelope.nonce),
+                Payload {
+                    msg: &envelope.wrapped_key,
+                    aad: envelope.collaborator.as_ref(),
+                },
+            )
+            .map_err(|_| Error::Decrypt)?;
+        Ok(ListKey(key.try_into().map_err(|_| Error::Decrypt)?))
+    }
+
+    /// Encrypt a description into the form stored on chain.
+    pub fn encrypt_description(&self, plaintext: &str) -> Result<String> {
+        let nonce = random_nonce();
+        let ciphertext = self
+            .cipher()
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_bytes())
+            .map_err(|_| Error::Encrypt)?;
+
+        let mut bytes = nonce.to_vec();
+        bytes.extend_from_slice(&ciphertext);
+        let encoded = STANDARD.encode(bytes);
+        if encoded.len() > MAX_ENCRYPTED_DESCRIPTION_LEN {
+            return Err(Error::DescriptionTooLong);
+        }
+        Ok(encoded)
+    }
+
+    /// Decrypt a description read from chain.
+    pub fn decrypt_description(&self, description: &str) -> Result<String> {
+        let bytes = STANDARD
+            .decode(description)
+            .map_err(|_| Error::InvalidCiphertext)?;
+        if bytes.len() < NONCE_LEN {
+            return Err(Error::InvalidCiphertext);
+        }
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher()
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| Error::Decrypt)?;
+        String::from_utf8(plaintext).map_err(|_| Error::InvalidCiphertext)
+    }
+
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(Key::from_slice(&self.0))
+    }
+}
+
+/// Items of `list` with their descriptions decrypted for `keypair`.
+pub fn decrypt_items(list: &TodoList, keypair: &Keypair) -> Result<Vec<TodoItem>> {
+    let key = ListKey::from_list(list, keypair)?;
+    list.items
+        .iter()
+        .map(|item| {
+            Ok(TodoItem {
+                description: key.decrypt_description(&item.description)?,
+                ..item.clone()
+            })
+        })
+        .collect()
+}
+
+/// `enable_encryption` for the owner's list, with the owner's envelope of `key`.
+pub fn enable_encryption_instruction(owner: &Pubkey, key: &ListKey) -> Result<Instruction> {
+    Ok(Instruction {
+        program_id: todo_list::ID,
+        accounts: manage_accounts(owner),
+        data: todo_list::instruction::EnableEncryption {
+            envelope: key.wrap_for(owner)?,
+        }
+        .data(),
+    })
+}
+
+/// `add_key_envelope` sharing `key` with `collaborator`.
+pub fn add_key_envelope_instruction(
+    owner: &Pubkey,
+    key: &ListKey,
+    collaborator: &Pubkey,
+) -> Result<Instruction> {
+    Ok(Instruction {
+        program_id: todo_list::ID,
+        accounts: manage_accounts(owner),
+        data: todo_list::instruction::AddKeyEnvelope {
+            envelope: key.wrap_for(collaborator)?,
+        }
+        .data(),
+    })
+}
+
+/// `add_item` with `plaintext` encrypted under `key`.
+pub fn add_item_instruction(owner: &Pubkey, key: &ListKey, plaintext: &str) -> Result<Instruction> {
+    Ok(Instruction {
+        program_id: todo_list::ID,
+        accounts: todo_list::accounts::AddItem {
+            todo_list: todo_list_address(owner),
+            owner: *owner,
+        }
+        .to_account_metas(None),
+        data: todo_list::instruction::AddItem {
+            description: key.encrypt_description(plaintext)?,
+        }
+        .data(),
+    })
+}
+
+fn todo_list_address(owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"todo-list", owner.as_ref()], &todo_list::ID).0
+}
+
+fn manage_accounts(owner: &Pubkey) -> Vec<solana_sdk::instruction::AccountMeta> {
+    todo_list::accounts::ManageKeyEnvelopes {
+        todo_list: todo_list_address(owner),
+        owner: *owner,
+    }
+    .to_account_metas(None)
+}
+
+// the x25519 secret matching an ed25519 keypair: the clamped lower half of
+// SHA-512 over the seed, i.e. the ed25519 signing scalar
+fn x25519_secret(keypair: &Keypair) -> StaticSecret {
+    let hash = Sha512::digest(&keypair.to_bytes()[..32]);
+    let mut scalar = [0; 32];
+    scalar.copy_from_slice(&hash[..32]);
+    StaticSecret::from(scalar)
+}
+
+// the x25519 public key matching an ed25519 public key
+fn x25519_public(pubkey: &Pubkey) -> Result<PublicKey> {
+    let point = CompressedEdwardsY(pubkey.to_bytes())
+        .decompress()
+        .ok_or(Error::InvalidPublicKey)?;
+    Ok(PublicKey::from(point.to_montgomery().to_bytes()))
+}
+
+// key-encryption cipher for an envelope, bound to both exchange keys
+fn envelope_cipher(
+    shared: &[u8; 32],
+    ephemeral: &PublicKey,
+    recipient: &PublicKey,
+) -> ChaCha20Poly1305 {
+    let kek = Sha256::new()
+        .chain_update(ENVELOPE_CONTEXT)
+        .chain_update(shared)
+        .chain_update(ephemeral.as_bytes())
+        .chain_update(recipient.as_bytes())
+        .finalize();
+    ChaCha20Poly1305::new(&kek)
+}
+
+fn random_nonce() -> [u8; NONCE_LEN] {
+    let mut nonce = [0; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+    nonce
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // bytes of plaintext whose ciphertext is exactly MAX_ENCRYPTED_DESCRIPTION_LEN
+    // once encoded: base64 of nonce, plaintext and the 16 byte tag
+    const MAX_PLAINTEXT_LEN: usize = MAX_ENCRYPTED_DESCRIPTION_LEN / 4 * 3 - NONCE_LEN - 16;
+
+    #[test]
+    fn x25519_keys_of_a_keypair_match() {
+        let keypair = Keypair::new();
+        let public = x25519_public(&keypair.pubkey()).unwrap();
+        assert_eq!(PublicKey::from(&x25519_secret(&keypair)), public);
+    }
+
+    #[test]
+    fn envelope_round_trip() {
+        let key = ListKey::generate();
+        let keypair = Keypair::new();
+        let envelope = key.wrap_for(&keypair.pubkey()).unwrap();
+        assert_eq!(envelope.collaborator, keypair.pubkey());
+
+        let unwrapped = ListKey::unwrap(&envelope, &keypair).unwrap();
+        assert_eq!(unwrapped.0, key.0);
+
+        // the envelope is of no use to anyone else
+        let other = Keypair::new();
+        assert!(matches!(ListKey::unwrap(&envelope, &other), Err(Error::Decrypt)));
+    }
+
+    #[test]
+    fn description_round_trip() {
+        let key = ListKey::generate();
+        let description = key.encrypt_description("Buy milk").unwrap();
+        assert_eq!(key.decrypt_description(&description).unwrap(), "Buy milk");
+    }
+
+    #[test]
+    fn wrong_key_does_not_decrypt() {
+        let description = ListKey::generate().encrypt_description("Buy milk").unwrap();
+        assert!(matches!(
+            ListKey::generate().decrypt_description(&description),
+            Err(Error::Decrypt)
+        ));
+    }
+
+    #[test]
+    fn tampered_description_does_not_decrypt() {
+        let key = ListKey::generate();
+        let bytes = STANDARD.decode(key.encrypt_description("Buy milk").unwrap()).unwrap();
+
+        // flip a bit of the nonce, then of the ciphertext
+        for at in [0, NONCE_LEN] {
+            let mut tampered = bytes.clone();
+            tampered[at] ^= 1;
+            assert!(matches!(
+                key.decrypt_description(&STANDARD.encode(tampered)),
+                Err(Error::Decrypt)
+            ));
+        }
+        assert!(matches!(key.decrypt_description("not base64!"), Err(Error::InvalidCiphertext)));
+        assert!(matches!(
+            key.decrypt_description(&STANDARD.encode([0; NONCE_LEN - 1])),
+            Err(Error::InvalidCiphertext)
+        ));
+    }
+
+    #[test]
+    fn description_length_limit() {
+        let key = ListKey::generate();
+        let longest = key.encrypt_description(&"x".repeat(MAX_PLAINTEXT_LEN)).unwrap();
+        assert_eq!(longest.len(), MAX_ENCRYPTED_DESCRIPTION_LEN);
+        assert!(matches!(
+            key.encrypt_description(&"x".repeat(MAX_PLAINTEXT_LEN + 1)),
+            Err(Error::DescriptionTooLong)
+        ));
+    }
+}
@@ -1,7 +1,20 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
 
 declare_id!("3d74iW95mWtMedyVCTZHGxWudAFJbrxtx1d7XoTrzJVw");
 
+// key envelopes a list can hold, the owner's included
+pub const MAX_KEY_ENVELOPES: usize = 4;
+// longest (base64 encoded) ciphertext accepted as a description of an encrypted list
+pub const MAX_ENCRYPTED_DESCRIPTION_LEN: usize = 256;
+// template names are used as a PDA seed, which is at most 32 bytes
+pub const MAX_TEMPLATE_NAME_LEN: usize = 32;
+// attachments a single item can carry
+pub const MAX_ATTACHMENTS: usize = 4;
+// room left for items in a list account (8 + 1000 bytes) once its other fields,
+// with a pending owner and no key envelopes, take their share
+pub const MAX_TEMPLATE_ITEMS_SIZE: usize = 1000 - (4 + 32 + 4 + 1 + 8 + 8 + 4 + 8 + 33 + 32 + 8 + 1 + 4);
+
 #[program]
 pub mod todo_list {
     use super::*;
@@ -17,6 +30,15 @@ pub mod todo_list {
         todo_list.owner = *ctx.accounts.user.key;
         todo_list.next_id = 0;
         todo_list.bump = ctx.bumps.todo_list;
+        todo_list.total_created = 0;
+        todo_list.total_completed = 0;
+        todo_list.open_count = 0;
+        todo_list.last_activity = Clock::get()?.unix_timestamp;
+        todo_list.pending_owner = None;
+        todo_list.archive_hash = [0; 32];
+        todo_list.archived_count = 0;
+        todo_list.encrypted = false;
+        todo_list.key_envelopes = Vec::new();
         Ok(())
     }
     
@@ -24,6 +46,11 @@ pub mod todo_list {
     pub fn add_item(ctx: Context<AddItem>, description: String) -> Result<()>{
         // Get the todo_list from the context
         let todo_list = &mut ctx.accounts.todo_list;
+
+        // encrypted lists carry ciphertext, which must stay within bounds
+        if todo_list.encrypted {
+            require!(description.len() <= MAX_ENCRYPTED_DESCRIPTION_LEN, ErrorCode::DescriptionTooLong);
+        }
         
         // create a new todoitem
         let item = TodoItem {
@@ -32,9 +59,15 @@ pub mod todo_list {
             // descripion from parameter
             description,
             completed: false, 
+            attachments: Vec::new(),
         };
         todo_list.items.push(item);
         todo_list.next_id += 1;
+
+        // keep the running stats up to date
+        todo_list.total_created += 1;
+        todo_list.open_count += 1;
+        todo_list.last_activity = Clock::get()?.unix_timestamp;
         Ok(())
     }
 
@@ -43,32 +76,278 @@ pub mod todo_list {
         // get the todo_list from the context
         let todo_list = &mut ctx.accounts.todo_list;
 
-        // find the item by id and mark it as completed
-        for item in &mut todo_list.items {
+        // find the item by id and flip its completed state
+        let completed = match todo_list.items.iter().find(|item| item.id == item_id) {
+            Some(item) => !item.completed,
+            None => return Err(ErrorCode::ItemNotFound.into()),
+        };
+        todo_list.set_completed(item_id, completed, Clock::get()?.unix_timestamp)
+    }
 
-            // if item found, toggle its completed state
-            if item.id == item_id {
-                item.completed = !item.completed;
-                return Ok(());
-            }
-        }
+    // mark an item as completed, doing nothing if it already is
+    pub fn complete_item(ctx: Context<CompleteItem>, item_id: u32) -> Result<()> {
+        let todo_list = &mut ctx.accounts.todo_list;
+        todo_list.set_completed(item_id, true, Clock::get()?.unix_timestamp)
+    }
 
-        // if item not found, return error
-        Err(ErrorCode::ItemNotFound.into())
+    // mark an item as open again, doing nothing if it already is
+    pub fn reopen_item(ctx: Context<ReopenItem>, item_id: u32) -> Result<()> {
+        let todo_list = &mut ctx.accounts.todo_list;
+        todo_list.set_completed(item_id, false, Clock::get()?.unix_timestamp)
     }
 
     pub fn delete_item(ctx: Context<DeleteItem>, item_id: u32) -> Result<()> {
         let todo_list = &mut ctx.accounts.todo_list;
         for i in 0..todo_list.items.len(){
             if todo_list.items[i].id == item_id {
-                todo_list.items.remove(i);
+                let item = todo_list.items.remove(i);
+                // an open item leaving the list lowers the open count
+                if !item.completed {
+                    todo_list.open_count -= 1;
+                }
+                todo_list.last_activity = Clock::get()?.unix_timestamp;
                 return Ok(());
             }
         }    
         Err(ErrorCode::ItemNotFound.into())
     }
 
-    
+
+    // link an item to a document or commit by its content hash
+    pub fn attach(ctx: Context<Attach>, item_id: u32, kind: AttachmentKind, digest: Vec<u8>) -> Result<()> {
+        require!(digest.len() == kind.digest_len(), ErrorCode::InvalidDigestLength);
+
+        let todo_list = &mut ctx.accounts.todo_list;
+        let item = todo_list
+            .items
+            .iter_mut()
+            .find(|item| item.id == item_id)
+            .ok_or(ErrorCode::ItemNotFound)?;
+        require!(item.attachments.len() < MAX_ATTACHMENTS, ErrorCode::TooManyAttachments);
+
+        let attachment = Attachment { kind, digest };
+        require!(!item.attachments.contains(&attachment), ErrorCode::DuplicateAttachment);
+        item.attachments.push(attachment);
+        todo_list.last_activity = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    // remove an attachment from an item
+    pub fn detach(ctx: Context<Detach>, item_id: u32, kind: AttachmentKind, digest: Vec<u8>) -> Result<()> {
+        let todo_list = &mut ctx.accounts.todo_list;
+        let item = todo_list
+            .items
+            .iter_mut()
+            .find(|item| item.id == item_id)
+            .ok_or(ErrorCode::ItemNotFound)?;
+
+        let attachment = Attachment { kind, digest };
+        let index = item
+            .attachments
+            .iter()
+            .position(|a| *a == attachment)
+            .ok_or(ErrorCode::AttachmentNotFound)?;
+        item.attachments.remove(index);
+        todo_list.last_activity = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    // move completed items out of the list, folding them into the archive hash
+    pub fn archive_completed(ctx: Context<ArchiveCompleted>) -> Result<()> {
+        let todo_list = &mut ctx.accounts.todo_list;
+
+        // split the completed items off, keeping the open ones in place
+        let (archived, open): (Vec<TodoItem>, Vec<TodoItem>) =
+            todo_list.items.drain(..).partition(|item| item.completed);
+        todo_list.items = open;
+        if archived.is_empty() {
+            return Ok(());
+        }
+
+        // fold each archived item into the running hash, in list order
+        for item in &archived {
+            todo_list.archive_hash = fold_archive_hash(todo_list.archive_hash, item)?;
+        }
+        todo_list.archived_count += archived.len() as u64;
+        todo_list.last_activity = Clock::get()?.unix_timestamp;
+
+        emit!(ItemsArchived {
+            todo_list: todo_list.key(),
+            items: archived,
+            archive_hash: todo_list.archive_hash,
+        });
+        Ok(())
+    }
+
+    // read the running stats of a list, returned as return data
+    pub fn get_stats(ctx: Context<GetStats>) -> Result<TodoStats> {
+        let todo_list = &ctx.accounts.todo_list;
+        Ok(TodoStats {
+            total_created: todo_list.total_created,
+            total_completed: todo_list.total_completed,
+            open_count: todo_list.open_count,
+            last_activity: todo_list.last_activity,
+        })
+    }
+
+    // first step of a transfer - the owner names who may take over the list
+    pub fn propose_owner(ctx: Context<ProposeOwner>, new_owner: Pubkey) -> Result<()> {
+        let todo_list = &mut ctx.accounts.todo_list;
+        require!(new_owner != todo_list.owner, ErrorCode::InvalidNewOwner);
+        todo_list.pending_owner = Some(new_owner);
+        Ok(())
+    }
+
+    // second step of a transfer - the proposed owner moves the list to a PDA
+    // under their own seed, the old PDA is closed and its rent goes back to
+    // the previous owner. an encrypted list needs an envelope for the new
+    // owner (see add_key_envelope) before it can be accepted
+    pub fn accept_owner(ctx: Context<AcceptOwner>) -> Result<()> {
+        let todo_list = &ctx.accounts.todo_list;
+        let new_owner = &ctx.accounts.new_owner;
+        require!(todo_list.pending_owner == Some(new_owner.key()), ErrorCode::NotPendingOwner);
+        if todo_list.encrypted {
+            require!(
+                todo_list.key_envelopes.iter().any(|e| e.collaborator == new_owner.key()),
+                ErrorCode::OwnerEnvelopeRequired
+            );
+        }
+
+        let new_todo_list = &mut ctx.accounts.new_todo_list;
+        new_todo_list.items = todo_list.items.clone();
+        new_todo_list.owner = new_owner.key();
+        new_todo_list.next_id = todo_list.next_id;
+        new_todo_list.bump = ctx.bumps.new_todo_list;
+        new_todo_list.total_created = todo_list.total_created;
+        new_todo_list.total_completed = todo_list.total_completed;
+        new_todo_list.open_count = todo_list.open_count;
+        new_todo_list.last_activity = Clock::get()?.unix_timestamp;
+        new_todo_list.pending_owner = None;
+        new_todo_list.archive_hash = todo_list.archive_hash;
+        new_todo_list.archived_count = todo_list.archived_count;
+        new_todo_list.encrypted = todo_list.encrypted;
+        // the previous owner loses access along with the list
+        new_todo_list.key_envelopes = todo_list
+            .key_envelopes
+            .iter()
+            .filter(|e| e.collaborator != todo_list.owner)
+            .cloned()
+            .collect();
+        Ok(())
+    }
+
+    // switch an empty list to encrypted descriptions, storing the owner's
+    // envelope of the list key
+    pub fn enable_encryption(ctx: Context<ManageKeyEnvelopes>, envelope: KeyEnvelope) -> Result<()> {
+        let todo_list = &mut ctx.accounts.todo_list;
+        require!(!todo_list.encrypted, ErrorCode::EncryptionAlreadyEnabled);
+        require!(todo_list.items.is_empty(), ErrorCode::ListNotEmpty);
+        require!(envelope.collaborator == todo_list.owner, ErrorCode::OwnerEnvelopeRequired);
+
+        todo_list.encrypted = true;
+        todo_list.key_envelopes = vec![envelope];
+        todo_list.last_activity = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    // share the list key with a collaborator
+    pub fn add_key_envelope(ctx: Context<ManageKeyEnvelopes>, envelope: KeyEnvelope) -> Result<()> {
+        let todo_list = &mut ctx.accounts.todo_list;
+        require!(todo_list.encrypted, ErrorCode::EncryptionNotEnabled);
+        require!(todo_list.key_envelopes.len() < MAX_KEY_ENVELOPES, ErrorCode::TooManyKeyEnvelopes);
+        require!(
+            !todo_list.key_envelopes.iter().any(|e| e.collaborator == envelope.collaborator),
+            ErrorCode::DuplicateKeyEnvelope
+        );
+
+        todo_list.key_envelopes.push(envelope);
+        todo_list.last_activity = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    // stop sharing the list key with a collaborator, the owner's envelope stays
+    pub fn remove_key_envelope(ctx: Context<ManageKeyEnvelopes>, collaborator: Pubkey) -> Result<()> {
+        let todo_list = &mut ctx.accounts.todo_list;
+        require!(collaborator != todo_list.owner, ErrorCode::OwnerEnvelopeRequired);
+
+        let index = todo_list
+            .key_envelopes
+            .iter()
+            .position(|e| e.collaborator == collaborator)
+            .ok_or(ErrorCode::KeyEnvelopeNotFound)?;
+        todo_list.key_envelopes.remove(index);
+        todo_list.last_activity = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    // publish a template checklist that anyone can start a list from
+    pub fn publish_template(ctx: Context<PublishTemplate>, name: String, descriptions: Vec<String>) -> Result<()> {
+        require!(name.len() <= MAX_TEMPLATE_NAME_LEN, ErrorCode::TemplateNameTooLong);
+        require!(template_items_size(&descriptions) <= MAX_TEMPLATE_ITEMS_SIZE, ErrorCode::TemplateTooLarge);
+        let template = &mut ctx.accounts.template;
+        template.author = ctx.accounts.author.key();
+        template.name = name;
+        template.descriptions = descriptions;
+        template.version = 1;
+        template.deprecated = false;
+        template.bump = ctx.bumps.template;
+        Ok(())
+    }
+
+    // replace the descriptions of a template, publishing it as a new version
+    pub fn update_template(ctx: Context<UpdateTemplate>, descriptions: Vec<String>) -> Result<()> {
+        let template = &mut ctx.accounts.template;
+        require!(!template.deprecated, ErrorCode::TemplateDeprecated);
+        require!(template_items_size(&descriptions) <= MAX_TEMPLATE_ITEMS_SIZE, ErrorCode::TemplateTooLarge);
+        template.descriptions = descriptions;
+        template.version += 1;
+        Ok(())
+    }
+
+    // stop new lists from being created from a template
+    pub fn deprecate_template(ctx: Context<UpdateTemplate>) -> Result<()> {
+        let template = &mut ctx.accounts.template;
+        template.deprecated = true;
+        Ok(())
+    }
+
+    // create the caller's list pre-filled with the items of a template, the
+    // caller names the version they expect so an update cannot surprise them
+    pub fn create_list_from_template(ctx: Context<CreateListFromTemplate>, version: u32) -> Result<()> {
+        let template = &ctx.accounts.template;
+        require!(!template.deprecated, ErrorCode::TemplateDeprecated);
+        require!(template.version == version, ErrorCode::TemplateVersionMismatch);
+
+        let items: Vec<TodoItem> = template
+            .descriptions
+            .iter()
+            .enumerate()
+            .map(|(id, description)| TodoItem {
+                id: id as u32,
+                description: description.clone(),
+                completed: false,
+                attachments: Vec::new(),
+            })
+            .collect();
+        let count = items.len() as u32;
+
+        let todo_list = &mut ctx.accounts.todo_list;
+        todo_list.items = items;
+        todo_list.owner = *ctx.accounts.user.key;
+        todo_list.next_id = count;
+        todo_list.bump = ctx.bumps.todo_list;
+        todo_list.total_created = count as u64;
+        todo_list.total_completed = 0;
+        todo_list.open_count = count;
+        todo_list.last_activity = Clock::get()?.unix_timestamp;
+        todo_list.pending_owner = None;
+        todo_list.archive_hash = [0; 32];
+        todo_list.archived_count = 0;
+        todo_list.encrypted = false;
+        todo_list.key_envelopes = Vec::new();
+        Ok(())
+    }
+
     // close list - remove it from chain 
     pub fn close_list(ctx: Context<CloseList>) -> Result<()> {
         let todo_list = &mut ctx.accounts.todo_list;
@@ -108,7 +387,108 @@ pub struct TodoList {
     pub items: Vec<TodoItem>,
     pub owner: Pubkey,
     pub next_id: u32,
-    pub bump: u8
+    pub bump: u8,
+    pub total_created: u64, // Items ever added to the list
+    pub total_completed: u64, // Times an item has been marked completed
+    pub open_count: u32, // Items in the list that are not completed
+    pub last_activity: i64, // Timestamp of the last change to the list
+    pub pending_owner: Option<Pubkey>, // Owner proposed by propose_owner, if any
+    pub archive_hash: [u8; 32], // Running SHA-256 over every archived item
+    pub archived_count: u64, // Items folded into archive_hash
+    pub encrypted: bool, // Whether item descriptions hold ciphertext
+    pub key_envelopes: Vec<KeyEnvelope>, // The list key, wrapped for each collaborator
+}
+
+impl TodoList {
+    // set the completed state of an item and update the stats, a no-op
+    // (besides the activity time) if the item is already in that state
+    pub fn set_completed(&mut self, item_id: u32, completed: bool, now: i64) -> Result<()> {
+        let item = self
+            .items
+            .iter_mut()
+            .find(|item| item.id == item_id)
+            .ok_or(ErrorCode::ItemNotFound)?;
+
+        if item.completed != completed {
+            item.completed = completed;
+            if completed {
+                self.total_completed += 1;
+                self.open_count -= 1;
+            } else {
+                self.open_count += 1;
+            }
+        }
+        self.last_activity = now;
+        Ok(())
+    }
+}
+
+// fold one item into an archive hash: sha256(hash || borsh(item))
+pub fn fold_archive_hash(hash: [u8; 32], item: &TodoItem) -> Result<[u8; 32]> {
+    let bytes = item.try_to_vec()?;
+    Ok(hashv(&[&hash, &bytes]).to_bytes())
+}
+
+// check that `item` was archived into a list whose archive hash is `archive_hash`.
+// `prefix_hash` is the archive hash right before the item was folded in and
+// `later_items` are the items archived after it, both recoverable from the
+// `ItemsArchived` events
+pub fn verify_archived_item(
+    prefix_hash: [u8; 32],
+    item: &TodoItem,
+    later_items: &[TodoItem],
+    archive_hash: [u8; 32],
+) -> Result<bool> {
+    let mut hash = fold_archive_hash(prefix_hash, item)?;
+    for later in later_items {
+        hash = fold_archive_hash(hash, later)?;
+    }
+    Ok(hash == archive_hash)
+}
+
+// bytes the items of a list created from these descriptions take: each item
+// is its id, description, completed flag and empty attachments
+pub fn template_items_size(descriptions: &[String]) -> usize {
+    descriptions
+        .iter()
+        .map(|description| 4 + (4 + description.len()) + 1 + 4)
+        .sum()
+}
+
+#[event]
+pub struct ItemsArchived {
+    pub todo_list: Pubkey,
+    pub items: Vec<TodoItem>,
+    pub archive_hash: [u8; 32],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct TodoStats {
+    pub total_created: u64,
+    pub total_completed: u64,
+    pub open_count: u32,
+    pub last_activity: i64,
+}
+
+#[account]
+pub struct TodoTemplate {
+    pub author: Pubkey, // The only key allowed to change the template
+    pub name: String,
+    pub descriptions: Vec<String>, // One new item per description
+    pub version: u32, // Bumped on every update
+    pub deprecated: bool, // Deprecated templates cannot be used anymore
+    pub bump: u8,
+}
+
+// the symmetric list key wrapped for one collaborator: an x25519 key
+// exchange between `ephemeral_public_key` and the collaborator's key
+// encrypts it with ChaCha20-Poly1305
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct KeyEnvelope {
+    pub collaborator: Pubkey,
+    pub ephemeral_public_key: [u8; 32],
+    pub nonce: [u8; 12],
+    pub wrapped_key: [u8; 48],
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
@@ -116,6 +496,32 @@ pub struct TodoItem {
     pub id: u32,
     pub description: String,
     pub completed: bool,
+    pub attachments: Vec<Attachment>,
+}
+
+// content hash of a document or commit linked to an item
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    pub kind: AttachmentKind,
+    pub digest: Vec<u8>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum AttachmentKind {
+    Sha256, // SHA-256 of a document
+    IpfsCidV1, // sha2-256 multihash digest of an IPFS CIDv1
+    GitCommit, // SHA-1 git commit id
+}
+
+impl AttachmentKind {
+    // the digest length this kind of attachment must have
+    pub fn digest_len(&self) -> usize {
+        match self {
+            AttachmentKind::Sha256 => 32,
+            AttachmentKind::IpfsCidV1 => 32,
+            AttachmentKind::GitCommit => 20,
+        }
+    }
 }
 
 #[derive(Accounts)]
@@ -132,6 +538,91 @@ pub struct CompleteItem<'info> {
     pub owner: Signer<'info>,
 }
 
+#[derive(Accounts)]
+pub struct ReopenItem<'info> {
+    #[account(mut, has_one = owner, seeds = [b"todo-list", owner.key().as_ref()], bump = todo_list.bump)]
+    pub todo_list: Account<'info, TodoList>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Attach<'info> {
+    #[account(mut, has_one = owner, seeds = [b"todo-list", owner.key().as_ref()], bump = todo_list.bump)]
+    pub todo_list: Account<'info, TodoList>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Detach<'info> {
+    #[account(mut, has_one = owner, seeds = [b"todo-list", owner.key().as_ref()], bump = todo_list.bump)]
+    pub todo_list: Account<'info, TodoList>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ArchiveCompleted<'info> {
+    #[account(mut, has_one = owner, seeds = [b"todo-list", owner.key().as_ref()], bump = todo_list.bump)]
+    pub todo_list: Account<'info, TodoList>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ManageKeyEnvelopes<'info> {
+    #[account(mut, has_one = owner, seeds = [b"todo-list", owner.key().as_ref()], bump = todo_list.bump)]
+    pub todo_list: Account<'info, TodoList>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct PublishTemplate<'info> {
+    #[account(
+        init,
+        payer = author,
+        space = 8 + 1000,
+        seeds = [b"template", author.key().as_ref(), name.as_bytes()],
+        bump
+    )]
+    pub template: Account<'info, TodoTemplate>,
+    #[account(mut)]
+    pub author: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateTemplate<'info> {
+    #[account(
+        mut,
+        has_one = author,
+        seeds = [b"template", author.key().as_ref(), template.name.as_bytes()],
+        bump = template.bump
+    )]
+    pub template: Account<'info, TodoTemplate>,
+    pub author: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateListFromTemplate<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 1000,
+        seeds = [b"todo-list", user.key().as_ref()],
+        bump
+    )]
+    pub todo_list: Account<'info, TodoList>,
+    #[account(seeds = [b"template", template.author.as_ref(), template.name.as_bytes()], bump = template.bump)]
+    pub template: Account<'info, TodoTemplate>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct GetStats<'info> {
+    pub todo_list: Account<'info, TodoList>,
+}
+
 #[derive(Accounts)]
 pub struct DeleteItem<'info> {
     #[account(mut, has_one = owner, seeds = [b"todo-list", owner.key().as_ref()], bump = todo_list.bump)]
@@ -139,12 +630,73 @@ pub struct DeleteItem<'info> {
     pub owner: Signer<'info>,
 }
 
+#[derive(Accounts)]
+pub struct ProposeOwner<'info> {
+    #[account(mut, has_one = owner, seeds = [b"todo-list", owner.key().as_ref()], bump = todo_list.bump)]
+    pub todo_list: Account<'info, TodoList>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptOwner<'info> {
+    #[account(mut, has_one = owner, seeds = [b"todo-list", owner.key().as_ref()], bump = todo_list.bump, close = owner)]
+    pub todo_list: Account<'info, TodoList>,
+    #[account(
+        init,
+        payer = new_owner,
+        space = 8 + 1000,
+        seeds = [b"todo-list", new_owner.key().as_ref()],
+        bump
+    )]
+    pub new_todo_list: Account<'info, TodoList>,
+    /// CHECK: the current owner of the list, pinned by `has_one` and only receives the closed account's rent
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub new_owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
 #[error_code]
 pub enum ErrorCode {
     #[msg("Item not found")]
     ItemNotFound,
     #[msg("Cannot close a list that is not empty")]
     ListNotEmpty,
+    #[msg("The new owner must differ from the current owner")]
+    InvalidNewOwner,
+    #[msg("Signer is not the pending owner of this list")]
+    NotPendingOwner,
+    #[msg("The list is already encrypted")]
+    EncryptionAlreadyEnabled,
+    #[msg("The list is not encrypted")]
+    EncryptionNotEnabled,
+    #[msg("The owner's key envelope is required")]
+    OwnerEnvelopeRequired,
+    #[msg("The list cannot hold more key envelopes")]
+    TooManyKeyEnvelopes,
+    #[msg("The collaborator already has a key envelope")]
+    DuplicateKeyEnvelope,
+    #[msg("Key envelope not found")]
+    KeyEnvelopeNotFound,
+    #[msg("The description is too long")]
+    DescriptionTooLong,
+    #[msg("The template name is too long")]
+    TemplateNameTooLong,
+    #[msg("The template is deprecated")]
+    TemplateDeprecated,
+    #[msg("The template is at a different version")]
+    TemplateVersionMismatch,
+    #[msg("The digest length does not match the attachment kind")]
+    InvalidDigestLength,
+    #[msg("The item cannot hold more attachments")]
+    TooManyAttachments,
+    #[msg("The item already has this attachment")]
+    DuplicateAttachment,
+    #[msg("Attachment not found")]
+    AttachmentNotFound,
+    #[msg("The template's items would not fit in a list")]
+    TemplateTooLarge,
 }
 
 #[derive(Accounts)]
@@ -153,4 +705,82 @@ pub struct CloseList<'info> {
     pub todo_list: Account<'info, TodoList>,
     #[account(mut)]
     pub owner: Signer<'info>,
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: u32, description: &str) -> TodoItem {
+        TodoItem {
+            id,
+            description: description.to_string(),
+            completed: true,
+            attachments: Vec::new(),
+        }
+    }
+
+    // the archive hash after folding `items` into an empty archive
+    fn archive(items: &[TodoItem]) -> [u8; 32] {
+        items
+            .iter()
+            .fold([0; 32], |hash, item| fold_archive_hash(hash, item).unwrap())
+    }
+
+    #[test]
+    fn archived_item_verifies() {
+        let items = [item(0, "first"), item(1, "second"), item(2, "third")];
+        let archive_hash = archive(&items);
+
+        let prefix_hash = archive(&items[..1]);
+        assert!(verify_archived_item(prefix_hash, &items[1], &items[2..], archive_hash).unwrap());
+        assert!(verify_archived_item([0; 32], &items[0], &items[1..], archive_hash).unwrap());
+    }
+
+    #[test]
+    fn tampered_item_does_not_verify() {
+        let items = [item(0, "first"), item(1, "second"), item(2, "third")];
+        let archive_hash = archive(&items);
+        let prefix_hash = archive(&items[..1]);
+
+        let tampered = item(1, "changed");
+        assert!(!verify_archived_item(prefix_hash, &tampered, &items[2..], archive_hash).unwrap());
+        let reopened = TodoItem {
+            completed: false,
+            ..items[1].clone()
+        };
+        assert!(!verify_archived_item(prefix_hash, &reopened, &items[2..], archive_hash).unwrap());
+    }
+
+    #[test]
+    fn largest_template_fits_in_a_list() {
+        // one 1 byte description short of the limit, then fill the rest
+        let mut descriptions = vec!["x".to_string(); MAX_TEMPLATE_ITEMS_SIZE / 14 - 1];
+        let left = MAX_TEMPLATE_ITEMS_SIZE - template_items_size(&descriptions);
+        descriptions.push("x".repeat(left - 13));
+        assert_eq!(template_items_size(&descriptions), MAX_TEMPLATE_ITEMS_SIZE);
+
+        let list = TodoList {
+            items: descriptions
+                .iter()
+                .enumerate()
+                .map(|(id, description)| TodoItem {
+                    completed: false,
+                    ..item(id as u32, description)
+                })
+                .collect(),
+            owner: Pubkey::new_unique(),
+            next_id: descriptions.len() as u32,
+            bump: 255,
+            total_created: descriptions.len() as u64,
+            total_completed: 0,
+            open_count: descriptions.len() as u32,
+            last_activity: 0,
+            pending_owner: Some(Pubkey::new_unique()),
+            archive_hash: [0; 32],
+            archived_count: 0,
+            encrypted: false,
+            key_envelopes: Vec::new(),
+        };
+        assert_eq!(list.try_to_vec().unwrap().len(), 1000);
+    }
+}
@@ -2,6 +2,7 @@ import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { TodoList } from "../target/types/todo_list";
 import { assert } from "chai";
+import { createHash } from "crypto";
 
 describe("todo_list", () => {
   // Configure the client to use the local cluster.
@@ -75,6 +76,109 @@ describe("todo_list", () => {
   });
 
 
+  it("Complete a todo item twice!", async () => {
+    const itemId = 0;
+
+    // a retried complete must not undo itself
+    for (let i = 0; i < 2; i++) {
+      await program.methods
+        .completeItem(itemId)
+        .accounts({
+          todoList: todoListPDA,
+          owner: provider.wallet.publicKey,
+        })
+        .rpc();
+    }
+
+    const todoListAccount = await program.account.todoList.fetch(todoListPDA);
+    assert.equal(todoListAccount.items[0].completed, true);
+    assert.equal(todoListAccount.totalCompleted.toNumber(), 1);
+    assert.equal(todoListAccount.openCount, 0);
+  });
+
+
+  it("Reopen a todo item twice!", async () => {
+    const itemId = 0;
+
+    for (let i = 0; i < 2; i++) {
+      await program.methods
+        .reopenItem(itemId)
+        .accounts({
+          todoList: todoListPDA,
+          owner: provider.wallet.publicKey,
+        })
+        .rpc();
+    }
+
+    const todoListAccount = await program.account.todoList.fetch(todoListPDA);
+    assert.equal(todoListAccount.items[0].completed, false);
+    assert.equal(todoListAccount.openCount, 1);
+  });
+
+
+  it("Get the list stats!", async () => {
+    const stats = await program.methods
+      .getStats()
+      .accounts({
+        todoList: todoListPDA,
+      })
+      .view();
+
+    assert.equal(stats.totalCreated.toNumber(), 1);
+    assert.equal(stats.totalCompleted.toNumber(), 1);
+    assert.equal(stats.openCount, 1);
+    assert.isAbove(stats.lastActivity.toNumber(), 0);
+  });
+
+
+  it("Archive completed todo items!", async () => {
+    // add a second item and complete it, leaving item 0 open
+    await program.methods
+      .addItem("Archive me")
+      .accounts({
+        todoList: todoListPDA,
+        owner: provider.wallet.publicKey,
+      })
+      .rpc();
+    await program.methods
+      .completeItem(1)
+      .accounts({
+        todoList: todoListPDA,
+        owner: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    await program.methods
+      .archiveCompleted()
+      .accounts({
+        todoList: todoListPDA,
+        owner: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    const todoListAccount = await program.account.todoList.fetch(todoListPDA);
+    assert.equal(todoListAccount.items.length, 1);
+    assert.equal(todoListAccount.items[0].id, 0);
+    assert.equal(todoListAccount.archivedCount.toNumber(), 1);
+
+    // the archive hash folds the item in as sha256(prev || borsh(item)),
+    // starting from all zeros
+    const description = Buffer.from("Archive me");
+    const item = Buffer.concat([
+      new anchor.BN(1).toArrayLike(Buffer, "le", 4), // id
+      new anchor.BN(description.length).toArrayLike(Buffer, "le", 4),
+      description,
+      Buffer.from([1]), // completed
+      new anchor.BN(0).toArrayLike(Buffer, "le", 4), // attachments
+    ]);
+    const expected = createHash("sha256")
+      .update(Buffer.alloc(32))
+      .update(item)
+      .digest();
+    assert.deepEqual(Buffer.from(todoListAccount.archiveHash), expected);
+  });
+
+
   it("Remove a todo item!", async () => {
     const itemId = 0;
 
@@ -88,6 +192,7 @@ describe("todo_list", () => {
 
     const todoListAccount = await program.account.todoList.fetch(todoListPDA);
     assert.equal(todoListAccount.items.length, 0);
+    assert.equal(todoListAccount.openCount, 0);
   });
 
 
@@ -106,4 +211,383 @@ describe("todo_list", () => {
     assert.equal(todoListAccountInfo, null);
   });
 
+
+  it("Transfer a todo list to a new owner!", async () => {
+    const newOwner = anchor.web3.Keypair.generate();
+    const [newTodoListPDA] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("todo-list"), newOwner.publicKey.toBuffer()],
+      program.programId
+    );
+
+    // the new owner pays for the new list account
+    const airdrop = await provider.connection.requestAirdrop(
+      newOwner.publicKey,
+      anchor.web3.LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdrop);
+
+    await program.methods
+      .createList()
+      .accounts({
+        todoList: todoListPDA,
+        user: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    await program.methods
+      .addItem("Hand over the list")
+      .accounts({
+        todoList: todoListPDA,
+        owner: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    await program.methods
+      .proposeOwner(newOwner.publicKey)
+      .accounts({
+        todoList: todoListPDA,
+        owner: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    await program.methods
+      .acceptOwner()
+      .accounts({
+        todoList: todoListPDA,
+        newTodoList: newTodoListPDA,
+        owner: provider.wallet.publicKey,
+        newOwner: newOwner.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([newOwner])
+      .rpc();
+
+    const oldAccountInfo = await provider.connection.getAccountInfo(
+      todoListPDA
+    );
+    assert.equal(oldAccountInfo, null);
+
+    const todoListAccount = await program.account.todoList.fetch(newTodoListPDA);
+    assert.ok(todoListAccount.owner.equals(newOwner.publicKey));
+    assert.equal(todoListAccount.items.length, 1);
+    assert.equal(todoListAccount.items[0].description, "Hand over the list");
+    assert.equal(todoListAccount.nextId, 1);
+    assert.equal(todoListAccount.pendingOwner, null);
+  });
+
+
+  it("Manage key envelopes of an encrypted list!", async () => {
+    const collaborator = anchor.web3.Keypair.generate().publicKey;
+    // the program only stores envelopes, their contents are opaque to it
+    const envelopeFor = (key: anchor.web3.PublicKey) => ({
+      collaborator: key,
+      ephemeralPublicKey: Array.from(anchor.web3.Keypair.generate().publicKey.toBytes()),
+      nonce: new Array(12).fill(1),
+      wrappedKey: new Array(48).fill(2),
+    });
+
+    // the wallet's previous list was handed over above, start a new one
+    await program.methods
+      .createList()
+      .accounts({
+        todoList: todoListPDA,
+        user: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    await program.methods
+      .enableEncryption(envelopeFor(provider.wallet.publicKey))
+      .accounts({
+        todoList: todoListPDA,
+        owner: provider.wallet.publicKey,
+      })
+      .rpc();
+    await program.methods
+      .addKeyEnvelope(envelopeFor(collaborator))
+      .accounts({
+        todoList: todoListPDA,
+        owner: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    let todoListAccount = await program.account.todoList.fetch(todoListPDA);
+    assert.equal(todoListAccount.encrypted, true);
+    assert.equal(todoListAccount.keyEnvelopes.length, 2);
+
+    try {
+      await program.methods
+        .addKeyEnvelope(envelopeFor(collaborator))
+        .accounts({
+          todoList: todoListPDA,
+          owner: provider.wallet.publicKey,
+        })
+        .rpc();
+      assert.fail("a second envelope for the same collaborator was accepted");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "DuplicateKeyEnvelope");
+    }
+
+    try {
+      await program.methods
+        .addItem("x".repeat(257))
+        .accounts({
+          todoList: todoListPDA,
+          owner: provider.wallet.publicKey,
+        })
+        .rpc();
+      assert.fail("an oversized ciphertext was accepted");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "DescriptionTooLong");
+    }
+
+    await program.methods
+      .removeKeyEnvelope(collaborator)
+      .accounts({
+        todoList: todoListPDA,
+        owner: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    todoListAccount = await program.account.todoList.fetch(todoListPDA);
+    assert.equal(todoListAccount.keyEnvelopes.length, 1);
+    assert.ok(todoListAccount.keyEnvelopes[0].collaborator.equals(provider.wallet.publicKey));
+  });
+
+
+  it("Transfer an encrypted todo list!", async () => {
+    const oldOwner = anchor.web3.Keypair.generate();
+    const newOwner = anchor.web3.Keypair.generate();
+    const [oldListPDA] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("todo-list"), oldOwner.publicKey.toBuffer()],
+      program.programId
+    );
+    const [newListPDA] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("todo-list"), newOwner.publicKey.toBuffer()],
+      program.programId
+    );
+    const envelopeFor = (key: anchor.web3.PublicKey) => ({
+      collaborator: key,
+      ephemeralPublicKey: Array.from(anchor.web3.Keypair.generate().publicKey.toBytes()),
+      nonce: new Array(12).fill(1),
+      wrappedKey: new Array(48).fill(2),
+    });
+    for (const keypair of [oldOwner, newOwner]) {
+      const airdrop = await provider.connection.requestAirdrop(
+        keypair.publicKey,
+        anchor.web3.LAMPORTS_PER_SOL
+      );
+      await provider.connection.confirmTransaction(airdrop);
+    }
+
+    await program.methods
+      .createList()
+      .accounts({
+        todoList: oldListPDA,
+        user: oldOwner.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([oldOwner])
+      .rpc();
+    await program.methods
+      .enableEncryption(envelopeFor(oldOwner.publicKey))
+      .accounts({
+        todoList: oldListPDA,
+        owner: oldOwner.publicKey,
+      })
+      .signers([oldOwner])
+      .rpc();
+    await program.methods
+      .proposeOwner(newOwner.publicKey)
+      .accounts({
+        todoList: oldListPDA,
+        owner: oldOwner.publicKey,
+      })
+      .signers([oldOwner])
+      .rpc();
+
+    const accept = () =>
+      program.methods
+        .acceptOwner()
+        .accounts({
+          todoList: oldListPDA,
+          newTodoList: newListPDA,
+          owner: oldOwner.publicKey,
+          newOwner: newOwner.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([newOwner])
+        .rpc();
+
+    // the new owner could not read the list without an envelope of its key
+    try {
+      await accept();
+      assert.fail("an encrypted list was handed over without the new owner's envelope");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "OwnerEnvelopeRequired");
+    }
+
+    await program.methods
+      .addKeyEnvelope(envelopeFor(newOwner.publicKey))
+      .accounts({
+        todoList: oldListPDA,
+        owner: oldOwner.publicKey,
+      })
+      .signers([oldOwner])
+      .rpc();
+    await accept();
+
+    // the previous owner's envelope does not move with the list
+    const todoListAccount = await program.account.todoList.fetch(newListPDA);
+    assert.equal(todoListAccount.keyEnvelopes.length, 1);
+    assert.ok(todoListAccount.keyEnvelopes[0].collaborator.equals(newOwner.publicKey));
+  });
+
+
+  it("Create a todo list from a template!", async () => {
+    const name = "onboarding";
+    const [templatePDA] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("template"),
+        provider.wallet.publicKey.toBuffer(),
+        Buffer.from(name),
+      ],
+      program.programId
+    );
+
+    await program.methods
+      .publishTemplate(name, ["Set up a wallet"])
+      .accounts({
+        template: templatePDA,
+        author: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    await program.methods
+      .updateTemplate(["Set up a wallet", "Read the docs"])
+      .accounts({
+        template: templatePDA,
+        author: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    const templateAccount = await program.account.todoTemplate.fetch(templatePDA);
+    assert.equal(templateAccount.version, 2);
+
+    // every item costs its description and 13 more bytes in a list
+    try {
+      await program.methods
+        .updateTemplate(new Array(70).fill("x"))
+        .accounts({
+          template: templatePDA,
+          author: provider.wallet.publicKey,
+        })
+        .rpc();
+      assert.fail("a template too large for a list was accepted");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "TemplateTooLarge");
+    }
+
+    // lists are created by a new user, the wallet already has one
+    const user = anchor.web3.Keypair.generate();
+    const [userListPDA] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("todo-list"), user.publicKey.toBuffer()],
+      program.programId
+    );
+    const airdrop = await provider.connection.requestAirdrop(
+      user.publicKey,
+      anchor.web3.LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdrop);
+
+    const createFromTemplate = (version: number) =>
+      program.methods
+        .createListFromTemplate(version)
+        .accounts({
+          todoList: userListPDA,
+          template: templatePDA,
+          user: user.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([user])
+        .rpc();
+
+    try {
+      await createFromTemplate(1);
+      assert.fail("an outdated template version was accepted");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "TemplateVersionMismatch");
+    }
+
+    await createFromTemplate(2);
+    const todoListAccount = await program.account.todoList.fetch(userListPDA);
+    assert.ok(todoListAccount.owner.equals(user.publicKey));
+    assert.deepEqual(
+      todoListAccount.items.map((item) => item.description),
+      ["Set up a wallet", "Read the docs"]
+    );
+    assert.equal(todoListAccount.nextId, 2);
+    assert.equal(todoListAccount.openCount, 2);
+
+    await program.methods
+      .deprecateTemplate()
+      .accounts({
+        template: templatePDA,
+        author: provider.wallet.publicKey,
+      })
+      .rpc();
+    const deprecated = await program.account.todoTemplate.fetch(templatePDA);
+    assert.equal(deprecated.deprecated, true);
+  });
+
+
+  it("Attach and detach content hashes!", async () => {
+    await program.methods
+      .addItem("Review the design doc")
+      .accounts({
+        todoList: todoListPDA,
+        owner: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    const digest = Buffer.alloc(32, 7);
+    await program.methods
+      .attach(0, { sha256: {} }, digest)
+      .accounts({
+        todoList: todoListPDA,
+        owner: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    // git commit ids are 20 bytes long
+    try {
+      await program.methods
+        .attach(0, { gitCommit: {} }, digest)
+        .accounts({
+          todoList: todoListPDA,
+          owner: provider.wallet.publicKey,
+        })
+        .rpc();
+      assert.fail("a 32 byte git commit id was accepted");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "InvalidDigestLength");
+    }
+
+    let todoListAccount = await program.account.todoList.fetch(todoListPDA);
+    assert.equal(todoListAccount.items[0].attachments.length, 1);
+    assert.deepEqual(todoListAccount.items[0].attachments[0].kind, { sha256: {} });
+    assert.ok(Buffer.from(todoListAccount.items[0].attachments[0].digest).equals(digest));
+
+    await program.methods
+      .detach(0, { sha256: {} }, digest)
+      .accounts({
+        todoList: todoListPDA,
+        owner: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    todoListAccount = await program.account.todoList.fetch(todoListPDA);
+    assert.equal(todoListAccount.items[0].attachments.length, 0);
+  });
+
 });
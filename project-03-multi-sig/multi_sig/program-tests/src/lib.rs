@@ -0,0 +1,217 @@
+//! A solana-program-test harness for the multi_sig program.
+//!
+//! By default the program is linked into the tests and runs natively:
+//!
+//! ```text
+//! cargo test --manifest-path program-tests/Cargo.toml
+//! ```
+//!
+//! To run the compiled program instead, build it and point
+//! solana-program-test at it:
+//!
+//! ```text
+//! anchor build
+//! SBF_OUT_DIR=$PWD/target/deploy cargo test --manifest-path program-tests/Cargo.toml
+//! ```
+
+use anchor_lang::solana_program::account_info::AccountInfo;
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::system_program;
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use multi_sig::{proposal_address, vault_address, MultiSigError, Owner, ProposalPayload};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::account::Account;
+use solana_sdk::clock::Clock;
+use solana_sdk::instruction::{AccountMeta, Instruction, InstructionError};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::{Transaction, TransactionError};
+
+pub struct Harness {
+    pub context: ProgramTestContext,
+}
+
+impl Harness {
+    /// Start a test validator running the program, with 1 SOL in each of `funded`.
+    pub async fn start(funded: &[&Keypair]) -> Self {
+        // solana-program-test loads the compiled program instead when SBF_OUT_DIR is set
+        let mut program_test = ProgramTest::new("multi_sig", multi_sig::ID, processor!(process_instruction));
+        for keypair in funded {
+            program_test.add_account(
+                keypair.pubkey(),
+                Account::new(LAMPORTS_PER_SOL, 0, &system_program::ID),
+            );
+        }
+        Harness {
+            context: program_test.start_with_context().await,
+        }
+    }
+
+    /// The key paying for every transaction, also the creator of multisigs.
+    pub fn payer(&self) -> Pubkey {
+        self.context.payer.pubkey()
+    }
+
+    /// Send `instructions` in one transaction signed by the payer and `signers`.
+    pub async fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        // a fresh blockhash each time, so retrying a failed transaction is not
+        // dropped as a duplicate
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let mut all = vec![&self.context.payer];
+        all.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.context.payer.pubkey()),
+            &all,
+            blockhash,
+        );
+        self.context.banks_client.process_transaction(transaction).await
+    }
+
+    /// Move the clock `seconds` forward, e.g. past a timelock.
+    pub async fn advance_clock(&mut self, seconds: i64) {
+        let mut clock: Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        clock.unix_timestamp += seconds;
+        self.context.set_sysvar(&clock);
+    }
+
+    /// Fetch and deserialize an account of the program.
+    pub async fn account<T: AccountDeserialize>(&mut self, address: Pubkey) -> T {
+        let account = self
+            .context
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+            .expect("the account exists");
+        T::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+}
+
+// the program's entrypoint for native runs. anchor's `entry` wants the
+// accounts to live as long as their infos, which the processor does not
+// promise, so they are leaked for the length of the test
+fn process_instruction<'a>(program_id: &Pubkey, accounts: &[AccountInfo<'a>], data: &[u8]) -> ProgramResult {
+    let accounts: &'a [AccountInfo<'a>] = Box::leak(accounts.to_vec().into_boxed_slice());
+    multi_sig::entry(program_id, accounts, data)
+}
+
+/// Assert that a transaction failed with `error` from the program, raised
+/// directly or by one of the instructions it called.
+pub fn assert_program_error(result: Result<(), BanksClientError>, error: MultiSigError) {
+    match result.expect_err("the transaction succeeded").unwrap() {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => assert_eq!(code, u32::from(error)),
+        other => panic!("expected {error:?}, got {other:?}"),
+    }
+}
+
+/// An owner with weight 1 and every permission.
+pub fn owner(key: &Pubkey) -> Owner {
+    Owner {
+        key: *key,
+        weight: 1,
+        permissions: Owner::ALL,
+    }
+}
+
+/// The multisig `creator` creates under `name` with nonce 0.
+pub fn multi_sig_address(creator: &Pubkey, name: &str) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"multi-sig", creator.as_ref(), name.as_bytes(), &[0]],
+        &multi_sig::ID,
+    )
+    .0
+}
+
+pub fn create_multi_sig(creator: &Pubkey, name: &str, owners: Vec<Owner>, threshold: u16) -> Instruction {
+    Instruction {
+        program_id: multi_sig::ID,
+        accounts: multi_sig::accounts::CreateMultiSig {
+            multi_sig: multi_sig_address(creator, name),
+            user: *creator,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: multi_sig::instruction::CreateMultiSig {
+            name: name.to_string(),
+            nonce: 0,
+            owners,
+            threshold,
+            timelock_seconds: 0,
+        }
+        .data(),
+    }
+}
+
+pub fn create_proposal(
+    multi_sig: &Pubkey,
+    proposal_id: u64,
+    proposer: &Pubkey,
+    instructions: &[Instruction],
+) -> Instruction {
+    Instruction {
+        program_id: multi_sig::ID,
+        accounts: multi_sig::accounts::CreateProposal {
+            multi_sig: *multi_sig,
+            proposal: proposal_address(multi_sig, proposal_id),
+            proposer: *proposer,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: multi_sig::instruction::CreateTransactionProposal {
+            instructions: ProposalPayload::new(instructions).encode(),
+            expires_at: None,
+        }
+        .data(),
+    }
+}
+
+pub fn approve(multi_sig: &Pubkey, proposal_id: u64, approver: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: multi_sig::ID,
+        accounts: multi_sig::accounts::ApproveTransaction {
+            multi_sig: *multi_sig,
+            proposal: proposal_address(multi_sig, proposal_id),
+            approver: *approver,
+        }
+        .to_account_metas(None),
+        data: multi_sig::instruction::ApproveTransaction { proposal_id }.data(),
+    }
+}
+
+/// execute_transaction for a proposal made of `instructions`, passing each
+/// program and its accounts as remaining accounts.
+pub fn execute(
+    multi_sig: &Pubkey,
+    proposal_id: u64,
+    executor: &Pubkey,
+    instructions: &[Instruction],
+) -> Instruction {
+    let vault = vault_address(multi_sig);
+    let mut accounts = multi_sig::accounts::ExecuteTransaction {
+        multi_sig: *multi_sig,
+        proposal: proposal_address(multi_sig, proposal_id),
+        vault,
+        executor: *executor,
+    }
+    .to_account_metas(None);
+    for ix in instructions {
+        accounts.push(AccountMeta::new_readonly(ix.program_id, false));
+        // the program signs for the vault, the transaction does not
+        accounts.extend(ix.accounts.iter().map(|meta| AccountMeta {
+            is_signer: meta.is_signer && meta.pubkey != vault,
+            ..meta.clone()
+        }));
+    }
+
+    Instruction {
+        program_id: multi_sig::ID,
+        accounts,
+        data: multi_sig::instruction::ExecuteTransaction { proposal_id }.data(),
+    }
+}
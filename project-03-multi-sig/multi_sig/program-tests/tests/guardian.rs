@@ -0,0 +1,205 @@
+// a guardian can pause a multisig and veto proposals in their timelock, but
+// cannot stop the owners from unpausing it
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use multi_sig::{proposal_address, vault_address, MultiSig, MultiSigError};
+use multi_sig_program_tests::*;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+fn set_guardians(multi_sig: &Pubkey, guardians: Vec<Pubkey>) -> Instruction {
+    Instruction {
+        program_id: multi_sig::ID,
+        accounts: multi_sig::accounts::SetGuardians {
+            multi_sig: *multi_sig,
+            vault: vault_address(multi_sig),
+        }
+        .to_account_metas(None),
+        data: multi_sig::instruction::SetGuardians { guardians }.data(),
+    }
+}
+
+fn update_timelock(multi_sig: &Pubkey, timelock_seconds: i64) -> Instruction {
+    Instruction {
+        program_id: multi_sig::ID,
+        accounts: multi_sig::accounts::UpdateTimelock {
+            multi_sig: *multi_sig,
+            vault: vault_address(multi_sig),
+        }
+        .to_account_metas(None),
+        data: multi_sig::instruction::UpdateTimelock { timelock_seconds }.data(),
+    }
+}
+
+fn pause(multi_sig: &Pubkey, guardian: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: multi_sig::ID,
+        accounts: multi_sig::accounts::Pause {
+            multi_sig: *multi_sig,
+            guardian: *guardian,
+        }
+        .to_account_metas(None),
+        data: multi_sig::instruction::Pause {}.data(),
+    }
+}
+
+fn unpause(multi_sig: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: multi_sig::ID,
+        accounts: multi_sig::accounts::Unpause {
+            multi_sig: *multi_sig,
+            vault: vault_address(multi_sig),
+        }
+        .to_account_metas(None),
+        data: multi_sig::instruction::Unpause {}.data(),
+    }
+}
+
+fn veto(multi_sig: &Pubkey, proposal_id: u64, guardian: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: multi_sig::ID,
+        accounts: multi_sig::accounts::Veto {
+            multi_sig: *multi_sig,
+            proposal: proposal_address(multi_sig, proposal_id),
+            guardian: *guardian,
+        }
+        .to_account_metas(None),
+        data: multi_sig::instruction::Veto { proposal_id }.data(),
+    }
+}
+
+// a multisig owned by `owner` alone and guarded by `guardian`, with its
+// guardian set by proposal 0
+async fn guarded(harness: &mut Harness, owner: &Keypair, guardian: &Pubkey) -> Pubkey {
+    let creator = harness.payer();
+    let multi_sig = multi_sig_address(&creator, "guarded");
+    let set = [set_guardians(&multi_sig, vec![*guardian])];
+    harness
+        .send(
+            &[
+                create_multi_sig(&creator, "guarded", vec![multi_sig_program_tests::owner(&owner.pubkey())], 1),
+                create_proposal(&multi_sig, 0, &owner.pubkey(), &set),
+                approve(&multi_sig, 0, &owner.pubkey()),
+                execute(&multi_sig, 0, &owner.pubkey(), &set),
+            ],
+            &[owner],
+        )
+        .await
+        .unwrap();
+    multi_sig
+}
+
+#[tokio::test]
+async fn guardian_cannot_veto_the_unpause() {
+    let (alice, guardian) = (Keypair::new(), Keypair::new());
+    let mut harness = Harness::start(&[&alice, &guardian]).await;
+    let multi_sig = guarded(&mut harness, &alice, &guardian.pubkey()).await;
+
+    harness
+        .send(&[pause(&multi_sig, &guardian.pubkey())], &[&guardian])
+        .await
+        .unwrap();
+
+    // a paused multisig still takes the proposal that unpauses it
+    let recover = [unpause(&multi_sig)];
+    harness
+        .send(
+            &[
+                create_proposal(&multi_sig, 1, &alice.pubkey(), &recover),
+                approve(&multi_sig, 1, &alice.pubkey()),
+            ],
+            &[&alice],
+        )
+        .await
+        .unwrap();
+    assert_program_error(
+        harness.send(&[veto(&multi_sig, 1, &guardian.pubkey())], &[&guardian]).await,
+        MultiSigError::RecoveryProposal,
+    );
+
+    harness
+        .send(&[execute(&multi_sig, 1, &alice.pubkey(), &recover)], &[&alice])
+        .await
+        .unwrap();
+    let account: MultiSig = harness.account(multi_sig).await;
+    assert!(!account.paused);
+}
+
+#[tokio::test]
+async fn guardian_can_veto_its_replacement_while_paused() {
+    let (alice, guardian) = (Keypair::new(), Keypair::new());
+    let mut harness = Harness::start(&[&alice, &guardian]).await;
+    let multi_sig = guarded(&mut harness, &alice, &guardian.pubkey()).await;
+
+    let timelock = [update_timelock(&multi_sig, 3600)];
+    harness
+        .send(
+            &[
+                create_proposal(&multi_sig, 1, &alice.pubkey(), &timelock),
+                approve(&multi_sig, 1, &alice.pubkey()),
+                execute(&multi_sig, 1, &alice.pubkey(), &timelock),
+            ],
+            &[&alice],
+        )
+        .await
+        .unwrap();
+
+    // a key that pushes through a new guardian set is stopped in its timelock,
+    // and the pause keeps it from trying again
+    let replace = [set_guardians(&multi_sig, vec![Pubkey::new_unique()])];
+    harness
+        .send(
+            &[
+                create_proposal(&multi_sig, 2, &alice.pubkey(), &replace),
+                approve(&multi_sig, 2, &alice.pubkey()),
+            ],
+            &[&alice],
+        )
+        .await
+        .unwrap();
+    harness
+        .send(
+            &[pause(&multi_sig, &guardian.pubkey()), veto(&multi_sig, 2, &guardian.pubkey())],
+            &[&guardian],
+        )
+        .await
+        .unwrap();
+    assert_program_error(
+        harness.send(&[execute(&multi_sig, 2, &alice.pubkey(), &replace)], &[&alice]).await,
+        MultiSigError::ProposalVetoed,
+    );
+    assert_program_error(
+        harness
+            .send(&[create_proposal(&multi_sig, 3, &alice.pubkey(), &replace)], &[&alice])
+            .await,
+        MultiSigError::MultiSigPaused,
+    );
+
+    let account: MultiSig = harness.account(multi_sig).await;
+    assert_eq!(account.guardians, vec![guardian.pubkey()]);
+}
+
+#[tokio::test]
+async fn veto_needs_a_running_timelock() {
+    let (alice, guardian) = (Keypair::new(), Keypair::new());
+    let mut harness = Harness::start(&[&alice, &guardian]).await;
+    let multi_sig = guarded(&mut harness, &alice, &guardian.pubkey()).await;
+
+    // without a timelock an approved proposal can run at once, leaving no
+    // window to veto it in
+    harness
+        .send(
+            &[
+                create_proposal(&multi_sig, 1, &alice.pubkey(), &[]),
+                approve(&multi_sig, 1, &alice.pubkey()),
+            ],
+            &[&alice],
+        )
+        .await
+        .unwrap();
+    assert_program_error(
+        harness.send(&[veto(&multi_sig, 1, &guardian.pubkey())], &[&guardian]).await,
+        MultiSigError::NotInTimelock,
+    );
+}
@@ -0,0 +1,114 @@
+// a council multisig with a team multisig's vault among its owners: the team
+// votes on council proposals by executing proposals of its own
+
+use multi_sig::{approve_as_vault, proposal_address, vault_address, MultiSigError, TransactionProposal};
+use multi_sig_program_tests::*;
+use solana_sdk::signature::{Keypair, Signer};
+
+#[tokio::test]
+async fn team_approves_council_proposal_through_its_own_proposal() {
+    let (alice, bob, carol) = (Keypair::new(), Keypair::new(), Keypair::new());
+    let mut harness = Harness::start(&[&alice, &bob, &carol]).await;
+    let creator = harness.payer();
+
+    // carol and the team's vault each hold one of the council's two votes
+    let team = multi_sig_address(&creator, "team");
+    let council = multi_sig_address(&creator, "council");
+    harness
+        .send(
+            &[
+                create_multi_sig(&creator, "team", vec![owner(&alice.pubkey()), owner(&bob.pubkey())], 2),
+                create_multi_sig(&creator, "council", vec![owner(&carol.pubkey()), owner(&vault_address(&team))], 2),
+            ],
+            &[],
+        )
+        .await
+        .unwrap();
+
+    harness
+        .send(
+            &[
+                create_proposal(&council, 0, &carol.pubkey(), &[]),
+                approve(&council, 0, &carol.pubkey()),
+            ],
+            &[&carol],
+        )
+        .await
+        .unwrap();
+
+    // the team's vote is a proposal of the team
+    let vote = [approve_as_vault(&council, 0, &team)];
+    harness
+        .send(
+            &[
+                create_proposal(&team, 0, &alice.pubkey(), &vote),
+                approve(&team, 0, &alice.pubkey()),
+            ],
+            &[&alice],
+        )
+        .await
+        .unwrap();
+
+    // the vault cannot vote before the team reaches its own threshold
+    assert_program_error(
+        harness.send(&[execute(&team, 0, &alice.pubkey(), &vote)], &[&alice]).await,
+        MultiSigError::NotEnoughApprovals,
+    );
+    let proposal: TransactionProposal = harness.account(proposal_address(&council, 0)).await;
+    assert!(!proposal.ready);
+
+    harness
+        .send(&[approve(&team, 0, &bob.pubkey())], &[&bob])
+        .await
+        .unwrap();
+    harness
+        .send(&[execute(&team, 0, &alice.pubkey(), &vote)], &[&alice])
+        .await
+        .unwrap();
+
+    let proposal: TransactionProposal = harness.account(proposal_address(&council, 0)).await;
+    assert_eq!(proposal.approvers, vec![carol.pubkey(), vault_address(&team)]);
+    assert!(proposal.ready);
+}
+
+#[tokio::test]
+async fn vault_of_another_multisig_is_not_an_owner() {
+    let alice = Keypair::new();
+    let mut harness = Harness::start(&[&alice]).await;
+    let creator = harness.payer();
+
+    // alice alone controls the outsider multisig, which is not on the council
+    let outsider = multi_sig_address(&creator, "outsider");
+    let council = multi_sig_address(&creator, "council");
+    harness
+        .send(
+            &[
+                create_multi_sig(&creator, "outsider", vec![owner(&alice.pubkey())], 1),
+                create_multi_sig(&creator, "council", vec![owner(&creator)], 1),
+                create_proposal(&council, 0, &creator, &[]),
+            ],
+            &[],
+        )
+        .await
+        .unwrap();
+
+    let vote = [approve_as_vault(&council, 0, &outsider)];
+    harness
+        .send(
+            &[
+                create_proposal(&outsider, 0, &alice.pubkey(), &vote),
+                approve(&outsider, 0, &alice.pubkey()),
+            ],
+            &[&alice],
+        )
+        .await
+        .unwrap();
+
+    // the outsider's proposal is approved, but its vault's vote is refused
+    assert_program_error(
+        harness.send(&[execute(&outsider, 0, &alice.pubkey(), &vote)], &[&alice]).await,
+        MultiSigError::NotAnOwner,
+    );
+    let proposal: TransactionProposal = harness.account(proposal_address(&council, 0)).await;
+    assert!(proposal.approvers.is_empty());
+}
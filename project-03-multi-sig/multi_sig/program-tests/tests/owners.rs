@@ -0,0 +1,121 @@
+// removing an owner through a proposal must leave an owner set that can still
+// reach the threshold and run proposals
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use multi_sig::{vault_address, MultiSig, MultiSigError, Owner};
+use multi_sig_program_tests::*;
+use solana_program_test::BanksClientError;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+fn remove_owner(multi_sig: &Pubkey, owner: Pubkey) -> Instruction {
+    Instruction {
+        program_id: multi_sig::ID,
+        accounts: multi_sig::accounts::RemoveOwner {
+            multi_sig: *multi_sig,
+            vault: vault_address(multi_sig),
+        }
+        .to_account_metas(None),
+        data: multi_sig::instruction::RemoveOwner { owner }.data(),
+    }
+}
+
+// propose, approve and execute `instructions` as `proposer`, the only approval
+// needed under a threshold of 1
+async fn run(
+    harness: &mut Harness,
+    multi_sig: &Pubkey,
+    proposer: &Keypair,
+    instructions: &[Instruction],
+) -> Result<(), BanksClientError> {
+    harness
+        .send(
+            &[
+                create_proposal(multi_sig, 0, &proposer.pubkey(), instructions),
+                approve(multi_sig, 0, &proposer.pubkey()),
+            ],
+            &[proposer],
+        )
+        .await
+        .unwrap();
+    harness
+        .send(&[execute(multi_sig, 0, &proposer.pubkey(), instructions)], &[proposer])
+        .await
+}
+
+#[tokio::test]
+async fn removal_cannot_break_the_threshold() {
+    let (alice, bob) = (Keypair::new(), Keypair::new());
+    let mut harness = Harness::start(&[&alice, &bob]).await;
+    let creator = harness.payer();
+
+    // both owners are needed, so neither can be removed
+    let multi_sig = multi_sig_address(&creator, "pair");
+    harness
+        .send(&[create_multi_sig(&creator, "pair", vec![owner(&alice.pubkey()), owner(&bob.pubkey())], 2)], &[])
+        .await
+        .unwrap();
+    let removal = [remove_owner(&multi_sig, bob.pubkey())];
+    harness
+        .send(
+            &[
+                create_proposal(&multi_sig, 0, &alice.pubkey(), &removal),
+                approve(&multi_sig, 0, &alice.pubkey()),
+            ],
+            &[&alice],
+        )
+        .await
+        .unwrap();
+    harness
+        .send(&[approve(&multi_sig, 0, &bob.pubkey())], &[&bob])
+        .await
+        .unwrap();
+    assert_program_error(
+        harness.send(&[execute(&multi_sig, 0, &alice.pubkey(), &removal)], &[&alice]).await,
+        MultiSigError::WouldBreakThreshold,
+    );
+
+    let account: MultiSig = harness.account(multi_sig).await;
+    assert_eq!(account.owners.len(), 2);
+}
+
+#[tokio::test]
+async fn removal_needs_an_owner() {
+    let alice = Keypair::new();
+    let mut harness = Harness::start(&[&alice]).await;
+    let creator = harness.payer();
+
+    let multi_sig = multi_sig_address(&creator, "solo");
+    harness
+        .send(&[create_multi_sig(&creator, "solo", vec![owner(&alice.pubkey())], 1)], &[])
+        .await
+        .unwrap();
+    assert_program_error(
+        run(&mut harness, &multi_sig, &alice, &[remove_owner(&multi_sig, Pubkey::new_unique())]).await,
+        MultiSigError::OwnerNotFound,
+    );
+}
+
+#[tokio::test]
+async fn removal_keeps_an_executor() {
+    let (alice, bob) = (Keypair::new(), Keypair::new());
+    let mut harness = Harness::start(&[&alice, &bob]).await;
+    let creator = harness.payer();
+
+    // bob can vote and propose but not execute, so alice must stay
+    let voter = Owner {
+        key: bob.pubkey(),
+        weight: 1,
+        permissions: Owner::INITIATE | Owner::VOTE,
+    };
+    let multi_sig = multi_sig_address(&creator, "roles");
+    harness
+        .send(&[create_multi_sig(&creator, "roles", vec![owner(&alice.pubkey()), voter], 1)], &[])
+        .await
+        .unwrap();
+    assert_program_error(
+        run(&mut harness, &multi_sig, &alice, &[remove_owner(&multi_sig, alice.pubkey())]).await,
+        MultiSigError::InoperableOwnerSet,
+    );
+}
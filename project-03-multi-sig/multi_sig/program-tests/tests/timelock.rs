@@ -0,0 +1,106 @@
+// the timelock is bounded, and changing it only applies to proposals opened
+// afterwards
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use multi_sig::{vault_address, MultiSigError, MAX_TIMELOCK_SECONDS};
+use multi_sig_program_tests::*;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+fn update_timelock(multi_sig: &Pubkey, timelock_seconds: i64) -> Instruction {
+    Instruction {
+        program_id: multi_sig::ID,
+        accounts: multi_sig::accounts::UpdateTimelock {
+            multi_sig: *multi_sig,
+            vault: vault_address(multi_sig),
+        }
+        .to_account_metas(None),
+        data: multi_sig::instruction::UpdateTimelock { timelock_seconds }.data(),
+    }
+}
+
+// propose and approve `instructions` as proposal `proposal_id`, the only
+// approval needed under a threshold of 1
+async fn approved(
+    harness: &mut Harness,
+    multi_sig: &Pubkey,
+    proposal_id: u64,
+    owner: &Keypair,
+    instructions: &[Instruction],
+) {
+    harness
+        .send(
+            &[
+                create_proposal(multi_sig, proposal_id, &owner.pubkey(), instructions),
+                approve(multi_sig, proposal_id, &owner.pubkey()),
+            ],
+            &[owner],
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn timelock_is_bounded() {
+    let alice = Keypair::new();
+    let mut harness = Harness::start(&[&alice]).await;
+    let creator = harness.payer();
+    let multi_sig = multi_sig_address(&creator, "locked");
+    harness
+        .send(&[create_multi_sig(&creator, "locked", vec![owner(&alice.pubkey())], 1)], &[])
+        .await
+        .unwrap();
+
+    // a timelock this long would overflow the timestamps it is added to
+    let update = [update_timelock(&multi_sig, i64::MAX)];
+    approved(&mut harness, &multi_sig, 0, &alice, &update).await;
+    assert_program_error(
+        harness.send(&[execute(&multi_sig, 0, &alice.pubkey(), &update)], &[&alice]).await,
+        MultiSigError::InvalidTimelock,
+    );
+
+    // a year is fine
+    let update = [update_timelock(&multi_sig, MAX_TIMELOCK_SECONDS)];
+    approved(&mut harness, &multi_sig, 1, &alice, &update).await;
+    harness
+        .send(&[execute(&multi_sig, 1, &alice.pubkey(), &update)], &[&alice])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn shorter_timelock_does_not_apply_to_approved_proposals() {
+    let alice = Keypair::new();
+    let mut harness = Harness::start(&[&alice]).await;
+    let creator = harness.payer();
+    let multi_sig = multi_sig_address(&creator, "locked");
+    harness
+        .send(&[create_multi_sig(&creator, "locked", vec![owner(&alice.pubkey())], 1)], &[])
+        .await
+        .unwrap();
+
+    let lock = [update_timelock(&multi_sig, 3600)];
+    approved(&mut harness, &multi_sig, 0, &alice, &lock).await;
+    harness
+        .send(&[execute(&multi_sig, 0, &alice.pubkey(), &lock)], &[&alice])
+        .await
+        .unwrap();
+
+    // proposal 2 is approved shortly before proposal 1 drops the timelock
+    let unlock = [update_timelock(&multi_sig, 0)];
+    approved(&mut harness, &multi_sig, 1, &alice, &unlock).await;
+    harness.advance_clock(3000).await;
+    approved(&mut harness, &multi_sig, 2, &alice, &[]).await;
+    harness.advance_clock(600).await;
+    harness
+        .send(&[execute(&multi_sig, 1, &alice.pubkey(), &unlock)], &[&alice])
+        .await
+        .unwrap();
+
+    // proposal 2 has only waited 600 of its 3600 seconds
+    assert_program_error(
+        harness.send(&[execute(&multi_sig, 2, &alice.pubkey(), &[])], &[&alice]).await,
+        MultiSigError::StaleProposal,
+    );
+}
@@ -0,0 +1,280 @@
+// SPL token payouts from the vault, through a transfer proposal or a spending
+// limit. the token accounts must hold the named mint, and belong to the vault
+// and the recipient
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use anchor_spl::token::TokenAccount;
+use multi_sig::{proposal_address, vault_address, MultiSigError, TransferProposal};
+use multi_sig_program_tests::*;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_system_interface::instruction::{create_account, transfer};
+
+// a new mint the payer can mint from
+async fn create_mint(harness: &mut Harness) -> Pubkey {
+    let mint = Keypair::new();
+    let payer = harness.payer();
+    harness
+        .send(
+            &[
+                create_account(
+                    &payer,
+                    &mint.pubkey(),
+                    Rent::default().minimum_balance(spl_token::state::Mint::LEN),
+                    spl_token::state::Mint::LEN as u64,
+                    &spl_token::ID,
+                ),
+                spl_token::instruction::initialize_mint2(&spl_token::ID, &mint.pubkey(), &payer, None, 0).unwrap(),
+            ],
+            &[&mint],
+        )
+        .await
+        .unwrap();
+    mint.pubkey()
+}
+
+// a new token account of `mint` owned by `owner`, holding `amount`
+async fn create_token_account(harness: &mut Harness, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Pubkey {
+    let account = Keypair::new();
+    let payer = harness.payer();
+    harness
+        .send(
+            &[
+                create_account(
+                    &payer,
+                    &account.pubkey(),
+                    Rent::default().minimum_balance(spl_token::state::Account::LEN),
+                    spl_token::state::Account::LEN as u64,
+                    &spl_token::ID,
+                ),
+                spl_token::instruction::initialize_account3(&spl_token::ID, &account.pubkey(), mint, owner).unwrap(),
+                spl_token::instruction::mint_to(&spl_token::ID, mint, &account.pubkey(), &payer, &[], amount).unwrap(),
+            ],
+            &[&account],
+        )
+        .await
+        .unwrap();
+    account.pubkey()
+}
+
+async fn balance(harness: &mut Harness, token_account: Pubkey) -> u64 {
+    harness.account::<TokenAccount>(token_account).await.amount
+}
+
+fn propose_transfer(multi_sig: &Pubkey, proposal_id: u64, proposer: &Pubkey, transfer: TransferProposal) -> Instruction {
+    Instruction {
+        program_id: multi_sig::ID,
+        accounts: multi_sig::accounts::ProposeTransfer {
+            multi_sig: *multi_sig,
+            proposal: proposal_address(multi_sig, proposal_id),
+            proposer: *proposer,
+            system_program: solana_system_interface::program::ID,
+        }
+        .to_account_metas(None),
+        data: multi_sig::instruction::ProposeTransfer {
+            transfer,
+            expires_at: None,
+        }
+        .data(),
+    }
+}
+
+fn execute_transfer(
+    multi_sig: &Pubkey,
+    proposal_id: u64,
+    executor: &Pubkey,
+    recipient: &Pubkey,
+    vault_token_account: &Pubkey,
+    recipient_token_account: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: multi_sig::ID,
+        accounts: multi_sig::accounts::ExecuteTransfer {
+            multi_sig: *multi_sig,
+            proposal: proposal_address(multi_sig, proposal_id),
+            vault: vault_address(multi_sig),
+            recipient: *recipient,
+            vault_token_account: Some(*vault_token_account),
+            recipient_token_account: Some(*recipient_token_account),
+            token_program: Some(spl_token::ID),
+            executor: *executor,
+            system_program: solana_system_interface::program::ID,
+        }
+        .to_account_metas(None),
+        data: multi_sig::instruction::ExecuteTransfer { proposal_id }.data(),
+    }
+}
+
+fn spending_limit_address(multi_sig: &Pubkey, limit_id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"spending-limit", multi_sig.as_ref(), &limit_id.to_le_bytes()],
+        &multi_sig::ID,
+    )
+    .0
+}
+
+fn add_spending_limit(multi_sig: &Pubkey, mint: &Pubkey, amount: u64, member: &Pubkey, destination: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: multi_sig::ID,
+        accounts: multi_sig::accounts::AddSpendingLimit {
+            multi_sig: *multi_sig,
+            spending_limit: spending_limit_address(multi_sig, 0),
+            vault: vault_address(multi_sig),
+            system_program: solana_system_interface::program::ID,
+        }
+        .to_account_metas(None),
+        data: multi_sig::instruction::AddSpendingLimit {
+            limit_id: 0,
+            mint: Some(*mint),
+            amount,
+            period_seconds: 24 * 60 * 60,
+            members: vec![*member],
+            destinations: vec![*destination],
+        }
+        .data(),
+    }
+}
+
+fn spend(
+    multi_sig: &Pubkey,
+    member: &Pubkey,
+    recipient: &Pubkey,
+    vault_token_account: &Pubkey,
+    recipient_token_account: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    Instruction {
+        program_id: multi_sig::ID,
+        accounts: multi_sig::accounts::Spend {
+            multi_sig: *multi_sig,
+            spending_limit: spending_limit_address(multi_sig, 0),
+            vault: vault_address(multi_sig),
+            recipient: *recipient,
+            vault_token_account: Some(*vault_token_account),
+            recipient_token_account: Some(*recipient_token_account),
+            token_program: Some(spl_token::ID),
+            member: *member,
+            system_program: solana_system_interface::program::ID,
+        }
+        .to_account_metas(None),
+        data: multi_sig::instruction::Spend { limit_id: 0, amount }.data(),
+    }
+}
+
+#[tokio::test]
+async fn transfer_proposal_pays_out_tokens() {
+    let alice = Keypair::new();
+    let bob = Pubkey::new_unique();
+    let mut harness = Harness::start(&[&alice]).await;
+    let creator = harness.payer();
+    let multi_sig = multi_sig_address(&creator, "treasury");
+    harness
+        .send(&[create_multi_sig(&creator, "treasury", vec![owner(&alice.pubkey())], 1)], &[])
+        .await
+        .unwrap();
+
+    let mint = create_mint(&mut harness).await;
+    let vault_tokens = create_token_account(&mut harness, &mint, &vault_address(&multi_sig), 100).await;
+    let bob_tokens = create_token_account(&mut harness, &mint, &bob, 0).await;
+
+    let payout = TransferProposal {
+        mint: Some(mint),
+        amount: 40,
+        recipient: bob,
+    };
+    harness
+        .send(
+            &[
+                propose_transfer(&multi_sig, 0, &alice.pubkey(), payout),
+                approve(&multi_sig, 0, &alice.pubkey()),
+            ],
+            &[&alice],
+        )
+        .await
+        .unwrap();
+
+    // tokens of another mint, and an account of bob's mint that is not bob's
+    let other_mint = create_mint(&mut harness).await;
+    let bob_other_tokens = create_token_account(&mut harness, &other_mint, &bob, 0).await;
+    let carol_tokens = create_token_account(&mut harness, &mint, &Pubkey::new_unique(), 0).await;
+    for wrong in [bob_other_tokens, carol_tokens] {
+        assert_program_error(
+            harness
+                .send(&[execute_transfer(&multi_sig, 0, &alice.pubkey(), &bob, &vault_tokens, &wrong)], &[&alice])
+                .await,
+            MultiSigError::AccountMismatch,
+        );
+    }
+
+    harness
+        .send(&[execute_transfer(&multi_sig, 0, &alice.pubkey(), &bob, &vault_tokens, &bob_tokens)], &[&alice])
+        .await
+        .unwrap();
+    assert_eq!(balance(&mut harness, vault_tokens).await, 60);
+    assert_eq!(balance(&mut harness, bob_tokens).await, 40);
+}
+
+#[tokio::test]
+async fn spending_limit_pays_out_tokens() {
+    let alice = Keypair::new();
+    let bob = Pubkey::new_unique();
+    let mut harness = Harness::start(&[&alice]).await;
+    let creator = harness.payer();
+    let multi_sig = multi_sig_address(&creator, "allowance");
+    let vault = vault_address(&multi_sig);
+    harness
+        .send(
+            &[
+                create_multi_sig(&creator, "allowance", vec![owner(&alice.pubkey())], 1),
+                // the vault pays the rent of its spending limits
+                transfer(&creator, &vault, LAMPORTS_PER_SOL),
+            ],
+            &[],
+        )
+        .await
+        .unwrap();
+
+    let mint = create_mint(&mut harness).await;
+    let vault_tokens = create_token_account(&mut harness, &mint, &vault, 100).await;
+    let bob_tokens = create_token_account(&mut harness, &mint, &bob, 0).await;
+
+    // alice may send bob up to 50 tokens a day without a proposal
+    let limit = [add_spending_limit(&multi_sig, &mint, 50, &alice.pubkey(), &bob)];
+    harness
+        .send(
+            &[
+                create_proposal(&multi_sig, 0, &alice.pubkey(), &limit),
+                approve(&multi_sig, 0, &alice.pubkey()),
+                execute(&multi_sig, 0, &alice.pubkey(), &limit),
+            ],
+            &[&alice],
+        )
+        .await
+        .unwrap();
+
+    let carol_tokens = create_token_account(&mut harness, &mint, &Pubkey::new_unique(), 0).await;
+    assert_program_error(
+        harness
+            .send(&[spend(&multi_sig, &alice.pubkey(), &bob, &vault_tokens, &carol_tokens, 30)], &[&alice])
+            .await,
+        MultiSigError::AccountMismatch,
+    );
+
+    harness
+        .send(&[spend(&multi_sig, &alice.pubkey(), &bob, &vault_tokens, &bob_tokens, 30)], &[&alice])
+        .await
+        .unwrap();
+    assert_program_error(
+        harness
+            .send(&[spend(&multi_sig, &alice.pubkey(), &bob, &vault_tokens, &bob_tokens, 30)], &[&alice])
+            .await,
+        MultiSigError::SpendingLimitExceeded,
+    );
+    assert_eq!(balance(&mut harness, vault_tokens).await, 70);
+    assert_eq!(balance(&mut harness, bob_tokens).await, 30);
+}
@@ -0,0 +1,67 @@
+// approvals signed offline: each owner signs `approval_message` for a proposal
+// and a relayer submits the signatures as Ed25519 program instructions next to
+// approve_with_signatures
+
+use anchor_lang::prelude::*;
+
+use crate::MultiSigError;
+
+// keeps approval signatures from being valid messages anywhere else
+pub const APPROVAL_DOMAIN: &[u8] = b"multi-sig approval";
+
+// size of one signature's offsets in an Ed25519 program instruction
+const OFFSETS_LEN: usize = 14;
+// instruction index meaning "this instruction" in those offsets
+const CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+// what an owner signs to approve proposal `proposal_id` with the given hash.
+// `approval_epoch` changes whenever an approval is revoked, so a signature
+// relayed before cannot restore the revoked approval
+pub fn approval_message(
+    multi_sig: &Pubkey,
+    proposal_id: u64,
+    approval_epoch: u32,
+    proposal_hash: &[u8; 32],
+) -> Vec<u8> {
+    [
+        APPROVAL_DOMAIN,
+        multi_sig.as_ref(),
+        &proposal_id.to_le_bytes(),
+        &approval_epoch.to_le_bytes(),
+        proposal_hash,
+    ]
+    .concat()
+}
+
+// the signers and messages an Ed25519 program instruction verified. only
+// signatures whose key and message are stored in the instruction itself are
+// accepted, so what is read here is what the program checked
+pub fn verified_signatures(data: &[u8]) -> Result<Vec<(Pubkey, &[u8])>> {
+    let count = *data.first().ok_or(MultiSigError::InvalidSignatureInstruction)? as usize;
+    let u16_at = |at: usize| {
+        data.get(at..at + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+            .ok_or(MultiSigError::InvalidSignatureInstruction)
+    };
+
+    (0..count)
+        .map(|i| {
+            // signature, public key and message offsets and instruction indexes
+            let at = 2 + i * OFFSETS_LEN;
+            for index_at in [at + 2, at + 6, at + 12] {
+                require!(u16_at(index_at)? == CURRENT_INSTRUCTION, MultiSigError::InvalidSignatureInstruction);
+            }
+            let key_offset = u16_at(at + 4)? as usize;
+            let message_offset = u16_at(at + 8)? as usize;
+            let message_len = u16_at(at + 10)? as usize;
+
+            let key = data
+                .get(key_offset..key_offset + 32)
+                .ok_or(MultiSigError::InvalidSignatureInstruction)?;
+            let message = data
+                .get(message_offset..message_offset + message_len)
+                .ok_or(MultiSigError::InvalidSignatureInstruction)?;
+            Ok((Pubkey::try_from(key).unwrap(), message))
+        })
+        .collect()
+}
@@ -0,0 +1,32 @@
+// addresses and instructions for off-chain tools and other programs working
+// with a multisig
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{InstructionData, ToAccountMetas};
+
+// the vault PDA of a multisig, which signs its executed proposals and can
+// itself be an owner of another multisig
+pub fn vault_address(multi_sig: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"vault", multi_sig.as_ref()], &crate::ID).0
+}
+
+pub fn proposal_address(multi_sig: &Pubkey, proposal_id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[b"proposal", multi_sig.as_ref(), &proposal_id.to_le_bytes()], &crate::ID).0
+}
+
+// approve_transaction on proposal `proposal_id` of `council`, voting as the
+// vault of `member`. a proposal of `member` carrying it casts that vote once
+// it executes
+pub fn approve_as_vault(council: &Pubkey, proposal_id: u64, member: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: crate::accounts::ApproveTransaction {
+            multi_sig: *council,
+            proposal: proposal_address(council, proposal_id),
+            approver: vault_address(member),
+        }
+        .to_account_metas(None),
+        data: crate::instruction::ApproveTransaction { proposal_id }.data(),
+    }
+}
@@ -1,7 +1,36 @@
 use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::hash::{hash, hashv};
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_lang::solana_program::sysvar::{self, instructions::load_instruction_at_checked};
+use anchor_lang::system_program;
+use anchor_spl::token::{self, Token, TokenAccount};
+
+pub mod approval;
+pub mod client;
+pub mod payload;
+
+pub use approval::{approval_message, verified_signatures};
+pub use client::{approve_as_vault, proposal_address, vault_address};
+pub use payload::{ProposalAccountMeta, ProposalInstruction, ProposalPayload, PAYLOAD_VERSION};
 
 declare_id!("77AHZUsXPhf1FAbibDzPxsFJbyRgxTFJjSPLDUp9GVfN");
 
+// owners a multisig can hold, its account is sized for this many
+pub const MAX_OWNERS: usize = 10;
+// destinations a spending limit can allow
+pub const MAX_LIMIT_DESTINATIONS: usize = 10;
+// guardians a multisig can have
+pub const MAX_GUARDIANS: usize = 3;
+// longest timelock a multisig can set, a year, which keeps the timestamps
+// it is added to far from overflowing
+pub const MAX_TIMELOCK_SECONDS: i64 = 365 * 24 * 60 * 60;
+// largest instructions payload a proposal buffer can assemble, keeping the
+// finalized proposal within the 10KiB an account can be created with
+pub const MAX_PROPOSAL_BUFFER_SIZE: u32 = 8 * 1024;
+
 #[program]
 pub mod multi_sig {
     use super::*;
@@ -11,79 +40,689 @@ pub mod multi_sig {
         Ok(())
     }
 
-    pub fn create_multi_sig(ctx: Context<CreateMultiSig>, name: String, nonce: u8, owners: Vec<Pubkey>, threshold: u8) -> Result<()> {
+    pub fn create_multi_sig(ctx: Context<CreateMultiSig>, name: String, nonce: u8, owners: Vec<Owner>, threshold: u16, timelock_seconds: i64) -> Result<()> {
         let multi_sig = &mut ctx.accounts.multi_sig;
 
         // validate inputs
-        require!(owners.len() as u8 >= threshold, MultiSigError::InvalidThreshold);
+        validate_owners(&owners)?;
+        let total_weight: u16 = owners.iter().map(Owner::voting_weight).sum();
+        require!(total_weight >= threshold, MultiSigError::InvalidThreshold);
         require!(threshold > 0, MultiSigError::InvalidThresholdGtZero);
+        require!((0..=MAX_TIMELOCK_SECONDS).contains(&timelock_seconds), MultiSigError::InvalidTimelock);
 
         multi_sig.name = name;
         multi_sig.owners = owners;
         multi_sig.threshold = threshold;
         multi_sig.next_proposal_id = 0;
+        multi_sig.timelock_seconds = timelock_seconds;
+        multi_sig.config_version = 0;
+        multi_sig.guardians = vec![];
+        multi_sig.paused = false;
         Ok(())
     }
 
-    pub fn add_owner(ctx: Context<AddOwner>, new_owner: Pubkey) -> Result<()> {
+    // add_owner, remove_owner and update_threshold must be signed by the vault,
+    // so they only run as an instruction of an approved proposal
+    pub fn add_owner(ctx: Context<AddOwner>, new_owner: Pubkey, weight: u8, permissions: u8) -> Result<()> {
         // get the mutlisig account, make sure its mutable
         let multi_sig = &mut ctx.accounts.multi_sig;
+        require!(!multi_sig.is_owner(&new_owner), MultiSigError::DuplicateOwner);
+        require!(multi_sig.owners.len() < MAX_OWNERS, MultiSigError::TooManyOwners);
+        require!(weight > 0, MultiSigError::InvalidWeight);
+        require!(valid_permissions(permissions), MultiSigError::InvalidPermissions);
         // add the new owner to the owners vector
-        multi_sig.owners.push(new_owner);
+        multi_sig.owners.push(Owner { key: new_owner, weight, permissions });
+        multi_sig.config_version += 1;
         Ok(())
     }
 
     pub fn remove_owner(ctx: Context<RemoveOwner>, owner: Pubkey) -> Result<()> {
         // get the multisig account, make sure its mutable
         let multi_sig = &mut ctx.accounts.multi_sig;
+        require!(multi_sig.is_owner(&owner), MultiSigError::OwnerNotFound);
+        let weight = multi_sig.weight_of(&[owner]);
+        // the remaining owners must still be able to reach the threshold
+        require!(multi_sig.total_weight() - weight >= multi_sig.threshold, MultiSigError::WouldBreakThreshold);
         // remove the owner from the owners vector
-        multi_sig.owners.retain(|&x| x != owner);
+        multi_sig.owners.retain(|x| x.key != owner);
+        require_operable(&multi_sig.owners)?;
+        multi_sig.config_version += 1;
         Ok(())
     }
     
-    pub fn update_threshold(ctx: Context<UpdateThreshold>, new_threshold: u8) -> Result<()> {
+    pub fn update_threshold(ctx: Context<UpdateThreshold>, new_threshold: u16) -> Result<()> {
         let multi_sig = &mut ctx.accounts.multi_sig;
         // validate new threshold
-        require!(multi_sig.owners.len() as u8 >= new_threshold, MultiSigError::InvalidThreshold);
+        require!(multi_sig.total_weight() >= new_threshold, MultiSigError::InvalidThreshold);
         require!(new_threshold > 0, MultiSigError::InvalidThresholdGtZero);
         // update threshold
         multi_sig.threshold = new_threshold;
+        multi_sig.config_version += 1;
         Ok(())
     }
     
-    pub fn create_transaction_proposal(ctx: Context<CreateProposal>, instructions: Vec<u8>) -> Result<()> {
+    pub fn update_timelock(ctx: Context<UpdateTimelock>, timelock_seconds: i64) -> Result<()> {
         let multi_sig = &mut ctx.accounts.multi_sig;
-        let proposer = &ctx.accounts.proposer;
-        let proposal_id = multi_sig.next_proposal_id;
-        multi_sig.next_proposal_id += 1;
-        TransactionProposal {
+        require!((0..=MAX_TIMELOCK_SECONDS).contains(&timelock_seconds), MultiSigError::InvalidTimelock);
+        multi_sig.timelock_seconds = timelock_seconds;
+        // a shorter timelock must not speed up proposals already approved
+        multi_sig.config_version += 1;
+        Ok(())
+    }
+
+    // guardians can pause the multisig or veto proposals, but never move funds
+    pub fn set_guardians(ctx: Context<SetGuardians>, guardians: Vec<Pubkey>) -> Result<()> {
+        require!(guardians.len() <= MAX_GUARDIANS, MultiSigError::TooManyGuardians);
+        ctx.accounts.multi_sig.guardians = guardians;
+        Ok(())
+    }
+
+    // a paused multisig only accepts and executes proposals that unpause it, so
+    // unpausing takes a full-threshold vote
+    pub fn pause(ctx: Context<Pause>) -> Result<()> {
+        let multi_sig = &mut ctx.accounts.multi_sig;
+        let guardian = &ctx.accounts.guardian;
+        require!(multi_sig.guardians.contains(&guardian.key()), MultiSigError::NotAGuardian);
+        multi_sig.paused = true;
+        emit!(MultiSigPaused {
+            multi_sig: multi_sig.key(),
+            guardian: guardian.key(),
+        });
+        Ok(())
+    }
+
+    pub fn unpause(ctx: Context<Unpause>) -> Result<()> {
+        ctx.accounts.multi_sig.paused = false;
+        Ok(())
+    }
+
+    // a guardian can stop a pending proposal, e.g. one pushed through with a
+    // compromised key while it waits out its timelock. proposals that unpause
+    // the multisig cannot be vetoed, so a compromised guardian cannot keep it
+    // paused. replacing the guardians can be, or a compromised key could remove
+    // them inside the timelock
+    pub fn veto(ctx: Context<Veto>, proposal_id: u64) -> Result<()> {
+        let multi_sig = &ctx.accounts.multi_sig;
+        let guardian = &ctx.accounts.guardian;
+        let proposal = &mut ctx.accounts.proposal;
+        require!(multi_sig.guardians.contains(&guardian.key()), MultiSigError::NotAGuardian);
+        proposal.require_active()?;
+        require!(!is_recovery(&proposal.instructions, &multi_sig.key()), MultiSigError::RecoveryProposal);
+        let approved_at = proposal.approved_at.ok_or(MultiSigError::NotInTimelock)?;
+        require!(
+            Clock::get()?.unix_timestamp < approved_at + multi_sig.timelock_seconds,
+            MultiSigError::NotInTimelock
+        );
+        proposal.status = ProposalStatus::Vetoed;
+        emit!(ProposalVetoed {
             multi_sig: multi_sig.key(),
-            proposer: proposer.key(),
-            instructions,
-            approvers: vec![],
-            executed: false,
-            created_at: Clock::get()?.unix_timestamp,
             proposal_id,
-        };
+            guardian: guardian.key(),
+        });
+        Ok(())
+    }
 
+    pub fn create_transaction_proposal(ctx: Context<CreateProposal>, instructions: Vec<u8>, expires_at: Option<i64>) -> Result<()> {
+        let bump = ctx.bumps.proposal;
+        open_proposal(
+            &mut ctx.accounts.multi_sig,
+            &mut ctx.accounts.proposal,
+            ctx.accounts.proposer.key(),
+            instructions,
+            None,
+            expires_at,
+            bump,
+        )
+    }
+
+    // propose paying SOL (no mint) or SPL tokens out of the vault
+    pub fn propose_transfer(ctx: Context<ProposeTransfer>, transfer: TransferProposal, expires_at: Option<i64>) -> Result<()> {
+        require!(transfer.amount > 0, MultiSigError::InvalidAmount);
+        let bump = ctx.bumps.proposal;
+        open_proposal(
+            &mut ctx.accounts.multi_sig,
+            &mut ctx.accounts.proposal,
+            ctx.accounts.proposer.key(),
+            vec![],
+            Some(transfer),
+            expires_at,
+            bump,
+        )
+    }
+
+    // payloads too large for one transaction are written to a buffer in chunks,
+    // then turned into a proposal once they match the declared hash
+    pub fn create_proposal_buffer(ctx: Context<CreateProposalBuffer>, final_size: u32, final_hash: [u8; 32]) -> Result<()> {
+        let multi_sig = &ctx.accounts.multi_sig;
+        let proposer = &ctx.accounts.proposer;
+        multi_sig.require_permission(&proposer.key(), Owner::INITIATE)?;
+        require!(final_size <= MAX_PROPOSAL_BUFFER_SIZE, MultiSigError::BufferTooLarge);
+
+        let buffer = &mut ctx.accounts.buffer;
+        buffer.multi_sig = multi_sig.key();
+        buffer.proposer = proposer.key();
+        buffer.final_hash = final_hash;
+        buffer.data = vec![0; final_size as usize];
+        buffer.bump = ctx.bumps.buffer;
+        Ok(())
+    }
+
+    pub fn append_to_buffer(ctx: Context<AppendToBuffer>, offset: u32, chunk: Vec<u8>) -> Result<()> {
+        let data = &mut ctx.accounts.buffer.data;
+        let start = offset as usize;
+        let end = start.checked_add(chunk.len()).ok_or(MultiSigError::ChunkOutOfBounds)?;
+        require!(end <= data.len(), MultiSigError::ChunkOutOfBounds);
+        data[start..end].copy_from_slice(&chunk);
+        Ok(())
+    }
+
+    // the buffer is closed into the proposal, refunding its rent
+    pub fn finalize_proposal(ctx: Context<FinalizeProposal>, expires_at: Option<i64>) -> Result<()> {
+        let buffer = &mut ctx.accounts.buffer;
+        require!(hash(&buffer.data).to_bytes() == buffer.final_hash, MultiSigError::BufferHashMismatch);
+
+        let bump = ctx.bumps.proposal;
+        open_proposal(
+            &mut ctx.accounts.multi_sig,
+            &mut ctx.accounts.proposal,
+            ctx.accounts.proposer.key(),
+            std::mem::take(&mut buffer.data),
+            None,
+            expires_at,
+            bump,
+        )
+    }
+
+    // abandon a buffer that will not be finalized
+    pub fn close_proposal_buffer(_ctx: Context<CloseProposalBuffer>) -> Result<()> {
         Ok(())
     }
 
     pub fn approve_transaction(ctx: Context<ApproveTransaction>, proposal_id: u64) -> Result<()> {
-        let multi_sig = &mut ctx.accounts.multi_sig;
+        let multi_sig = &ctx.accounts.multi_sig;
         let approver = &ctx.accounts.approver;
-        // find the proposal by id and add the approver
-        
+        let proposal = &mut ctx.accounts.proposal;
+
+        // only voting owners can approve, once, and only while the proposal is pending
+        multi_sig.require_permission(&approver.key(), Owner::VOTE)?;
+        proposal.require_active()?;
+        proposal.require_current(multi_sig)?;
+        require!(!proposal.approvers.contains(&approver.key()), MultiSigError::AlreadyApproved);
+        require!(!proposal.rejecters.contains(&approver.key()), MultiSigError::AlreadyRejected);
+
+        // add the approver to the proposal
+        proposal.approvers.push(approver.key());
+        check_ready(multi_sig, proposal, proposal_id)
+    }
+
+    // record the approvals of every owner whose signature over the proposal's
+    // approval_message was verified by an Ed25519 program instruction of this
+    // transaction, so one relayer can submit approvals signed offline
+    pub fn approve_with_signatures(ctx: Context<ApproveWithSignatures>, proposal_id: u64) -> Result<()> {
+        let multi_sig = &ctx.accounts.multi_sig;
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.require_active()?;
+        proposal.require_current(multi_sig)?;
+
+        let message = approval_message(&multi_sig.key(), proposal_id, proposal.approval_epoch, &proposal.hash());
+        let mut recorded = 0;
+        let mut index = 0;
+        // loading past the last instruction of the transaction fails
+        while let Ok(ix) = load_instruction_at_checked(index, &ctx.accounts.instructions_sysvar) {
+            index += 1;
+            if ix.program_id != ed25519_program::ID {
+                continue;
+            }
+            for (signer, signed) in verified_signatures(&ix.data)? {
+                // signatures of non-voters, over other messages or from owners
+                // who already voted are skipped
+                if signed != message.as_slice()
+                    || !multi_sig.has_permission(&signer, Owner::VOTE)
+                    || proposal.approvers.contains(&signer)
+                    || proposal.rejecters.contains(&signer)
+                {
+                    continue;
+                }
+                proposal.approvers.push(signer);
+                recorded += 1;
+            }
+        }
+        require!(recorded > 0, MultiSigError::NoValidSignatures);
+        check_ready(multi_sig, proposal, proposal_id)
+    }
+
+    pub fn reject_transaction(ctx: Context<RejectTransaction>, proposal_id: u64) -> Result<()> {
+        let multi_sig = &ctx.accounts.multi_sig;
+        let rejecter = &ctx.accounts.rejecter;
+        let proposal = &mut ctx.accounts.proposal;
 
+        // an owner either approves or rejects a proposal, not both
+        multi_sig.require_permission(&rejecter.key(), Owner::VOTE)?;
+        proposal.require_active()?;
+        proposal.require_current(multi_sig)?;
+        require!(!proposal.approvers.contains(&rejecter.key()), MultiSigError::AlreadyApproved);
+        require!(!proposal.rejecters.contains(&rejecter.key()), MultiSigError::AlreadyRejected);
+
+        proposal.rejecters.push(rejecter.key());
+
+        // once the owners who have not rejected cannot reach the threshold, the proposal is dead
+        let rejected_weight = multi_sig.weight_of(&proposal.rejecters);
+        if multi_sig.total_weight() - rejected_weight < multi_sig.threshold {
+            proposal.status = ProposalStatus::Rejected;
+            emit!(ProposalRejected {
+                multi_sig: multi_sig.key(),
+                proposal_id,
+                rejected_weight,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn revoke_approval(ctx: Context<RevokeApproval>, _proposal_id: u64) -> Result<()> {
+        let multi_sig = &ctx.accounts.multi_sig;
+        let approver = &ctx.accounts.approver;
+        let proposal = &mut ctx.accounts.proposal;
+
+        proposal.require_active()?;
+        let index = proposal
+            .approvers
+            .iter()
+            .position(|a| *a == approver.key())
+            .ok_or(MultiSigError::NotApproved)?;
+        proposal.approvers.remove(index);
+        // approvals signed offline before now no longer verify
+        proposal.approval_epoch += 1;
+        proposal.ready = multi_sig.weight_of(&proposal.approvers) >= multi_sig.threshold;
+        // dropping below the threshold restarts the timelock on the next approval
+        if !proposal.ready {
+            proposal.approved_at = None;
+        }
+        Ok(())
+    }
+
+    pub fn cancel_proposal(ctx: Context<CancelProposal>, _proposal_id: u64) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+        // only the proposer can withdraw a pending proposal
+        require_keys_eq!(proposal.proposer, ctx.accounts.proposer.key(), MultiSigError::NotProposer);
+        proposal.require_active()?;
+        proposal.status = ProposalStatus::Cancelled;
+        Ok(())
+    }
+
+    pub fn execute_transaction<'info>(ctx: Context<'_, '_, 'info, 'info, ExecuteTransaction<'info>>, proposal_id: u64) -> Result<()> {
+        let multi_sig = &ctx.accounts.multi_sig;
+        let executor = &ctx.accounts.executor;
+        let proposal = &mut ctx.accounts.proposal;
+
+        check_executable(multi_sig, proposal, &executor.key())?;
+        require!(proposal.transfer.is_none(), MultiSigError::WrongProposalKind);
+
+        let instructions = ProposalPayload::decode(&proposal.instructions)?.instructions;
+
+        // mark it executed and store that before running the instructions, anchor
+        // would only write the account back at the end, so until then a nested
+        // call would still find the proposal active and could execute it again
+        proposal.status = ProposalStatus::Executed;
+        proposal.exit(&crate::ID)?;
+
+        let multi_sig_key = multi_sig.key();
+        let vault_seeds: &[&[u8]] = &[b"vault", multi_sig_key.as_ref(), &[ctx.bumps.vault]];
+        let vault_key = ctx.accounts.vault.key();
+
+        // remaining_accounts holds, per instruction, the program followed by its accounts
+        let mut remaining = ctx.remaining_accounts;
+        for instruction in instructions {
+            let count = 1 + instruction.accounts.len();
+            require!(remaining.len() >= count, MultiSigError::AccountMismatch);
+            let (account_infos, rest) = remaining.split_at(count);
+            remaining = rest;
+
+            require_keys_eq!(account_infos[0].key(), instruction.program_id, MultiSigError::AccountMismatch);
+            for (meta, info) in instruction.accounts.iter().zip(&account_infos[1..]) {
+                require_keys_eq!(info.key(), meta.pubkey, MultiSigError::AccountMismatch);
+                require!(!meta.is_writable || info.is_writable, MultiSigError::AccountMismatch);
+                // only the vault signs through the program, anyone else must have signed the transaction
+                require!(!meta.is_signer || meta.pubkey == vault_key || info.is_signer, MultiSigError::AccountMismatch);
+            }
+
+            invoke_signed(&Instruction::from(&instruction), account_infos, &[vault_seeds])?;
+        }
+        // every supplied account must belong to an instruction
+        require!(remaining.is_empty(), MultiSigError::AccountMismatch);
+
+        emit!(ProposalExecuted {
+            multi_sig: multi_sig_key,
+            proposal_id,
+            executor: executor.key(),
+        });
         Ok(())
     }
-    // Additional functions to be implemented:
-    // approve transaction
-    // execute transaction
 
+    pub fn execute_transfer(ctx: Context<ExecuteTransfer>, proposal_id: u64) -> Result<()> {
+        let multi_sig = &ctx.accounts.multi_sig;
+        let executor = &ctx.accounts.executor;
+        let proposal = &mut ctx.accounts.proposal;
 
+        check_executable(multi_sig, proposal, &executor.key())?;
+        let transfer = proposal.transfer.clone().ok_or(MultiSigError::WrongProposalKind)?;
+        proposal.status = ProposalStatus::Executed;
+
+        let multi_sig_key = multi_sig.key();
+        Payout {
+            multi_sig: multi_sig_key,
+            vault: &ctx.accounts.vault,
+            vault_bump: ctx.bumps.vault,
+            recipient: ctx.accounts.recipient.to_account_info(),
+            vault_token_account: ctx.accounts.vault_token_account.as_ref(),
+            recipient_token_account: ctx.accounts.recipient_token_account.as_ref(),
+            token_program: ctx.accounts.token_program.as_ref(),
+            system_program: &ctx.accounts.system_program,
+        }
+        .pay(&transfer)?;
+
+        emit!(ProposalExecuted {
+            multi_sig: multi_sig_key,
+            proposal_id,
+            executor: executor.key(),
+        });
+        Ok(())
+    }
+
+    // spending limits are created and removed by the vault, i.e. through a
+    // proposal, which also pays for their rent
+    pub fn add_spending_limit(
+        ctx: Context<AddSpendingLimit>,
+        limit_id: u64,
+        mint: Option<Pubkey>,
+        amount: u64,
+        period_seconds: i64,
+        members: Vec<Pubkey>,
+        destinations: Vec<Pubkey>,
+    ) -> Result<()> {
+        let multi_sig = &ctx.accounts.multi_sig;
+        require!(amount > 0, MultiSigError::InvalidAmount);
+        require!(period_seconds > 0, MultiSigError::InvalidPeriod);
+        require!(!members.is_empty() && members.len() <= MAX_OWNERS, MultiSigError::InvalidLimitMembers);
+        require!(members.iter().all(|m| multi_sig.is_owner(m)), MultiSigError::NotAnOwner);
+        require!(destinations.len() <= MAX_LIMIT_DESTINATIONS, MultiSigError::TooManyDestinations);
+
+        let limit = &mut ctx.accounts.spending_limit;
+        limit.multi_sig = multi_sig.key();
+        limit.limit_id = limit_id;
+        limit.mint = mint;
+        limit.amount = amount;
+        limit.period_seconds = period_seconds;
+        limit.remaining = amount;
+        limit.last_reset = Clock::get()?.unix_timestamp;
+        limit.members = members;
+        limit.destinations = destinations;
+        limit.bump = ctx.bumps.spending_limit;
+        Ok(())
+    }
+
+    // closing the limit returns its rent to the vault
+    pub fn remove_spending_limit(_ctx: Context<RemoveSpendingLimit>, _limit_id: u64) -> Result<()> {
+        Ok(())
+    }
+
+    // a member of a spending limit pays out of the vault without a proposal,
+    // up to what is left of the limit in the current period
+    pub fn spend(ctx: Context<Spend>, limit_id: u64, amount: u64) -> Result<()> {
+        let multi_sig = &ctx.accounts.multi_sig;
+        let member = &ctx.accounts.member;
+        let limit = &mut ctx.accounts.spending_limit;
+        let recipient = ctx.accounts.recipient.key();
+
+        require!(!multi_sig.paused, MultiSigError::MultiSigPaused);
+        // members who are no longer owners lose their allowance
+        require!(limit.members.contains(&member.key()), MultiSigError::NotALimitMember);
+        require!(multi_sig.is_owner(&member.key()), MultiSigError::NotAnOwner);
+        require!(
+            limit.destinations.is_empty() || limit.destinations.contains(&recipient),
+            MultiSigError::DestinationNotAllowed
+        );
+        require!(amount > 0, MultiSigError::InvalidAmount);
+
+        limit.reset_if_due(Clock::get()?.unix_timestamp);
+        limit.remaining = limit
+            .remaining
+            .checked_sub(amount)
+            .ok_or(MultiSigError::SpendingLimitExceeded)?;
+
+        let multi_sig_key = multi_sig.key();
+        Payout {
+            multi_sig: multi_sig_key,
+            vault: &ctx.accounts.vault,
+            vault_bump: ctx.bumps.vault,
+            recipient: ctx.accounts.recipient.to_account_info(),
+            vault_token_account: ctx.accounts.vault_token_account.as_ref(),
+            recipient_token_account: ctx.accounts.recipient_token_account.as_ref(),
+            token_program: ctx.accounts.token_program.as_ref(),
+            system_program: &ctx.accounts.system_program,
+        }
+        .pay(&TransferProposal {
+            mint: limit.mint,
+            amount,
+            recipient,
+        })?;
+
+        emit!(SpendingLimitUsed {
+            multi_sig: multi_sig_key,
+            limit_id,
+            member: member.key(),
+            amount,
+            remaining: limit.remaining,
+        });
+        Ok(())
+    }
+
+    // anyone may close a proposal that can no longer run, the rent goes back
+    // to the proposer
+    pub fn close_proposal(ctx: Context<CloseProposal>, _proposal_id: u64) -> Result<()> {
+        require!(ctx.accounts.proposal.closable(&ctx.accounts.multi_sig), MultiSigError::ProposalStillActive);
+        Ok(())
+    }
 
+    // close_proposal for many proposals, passed as (proposal, proposer) pairs
+    // in remaining_accounts
+    pub fn close_proposals<'info>(ctx: Context<'_, '_, 'info, 'info, CloseProposals<'info>>) -> Result<()> {
+        let multi_sig = &ctx.accounts.multi_sig;
+        let pairs = ctx.remaining_accounts.chunks_exact(2);
+        require!(pairs.remainder().is_empty(), MultiSigError::AccountMismatch);
 
+        for pair in pairs {
+            let (proposal_info, proposer) = (&pair[0], &pair[1]);
+            // the owner and discriminator checks make this a genuine proposal
+            let proposal = Account::<TransactionProposal>::try_from(proposal_info)?;
+            require_keys_eq!(proposal.multi_sig, multi_sig.key(), MultiSigError::AccountMismatch);
+            require_keys_eq!(proposal.proposer, proposer.key(), MultiSigError::AccountMismatch);
+            require!(proposal_info.is_writable && proposer.is_writable, MultiSigError::AccountMismatch);
+            require!(proposal.closable(multi_sig), MultiSigError::ProposalStillActive);
+            proposal.close(proposer.clone())?;
+        }
+        Ok(())
+    }
+}
+
+// set up a new proposal under the multisig's next proposal id
+fn open_proposal(
+    multi_sig: &mut Account<MultiSig>,
+    proposal: &mut Account<TransactionProposal>,
+    proposer: Pubkey,
+    instructions: Vec<u8>,
+    transfer: Option<TransferProposal>,
+    expires_at: Option<i64>,
+    bump: u8,
+) -> Result<()> {
+    // only owners who may initiate can propose transactions
+    multi_sig.require_permission(&proposer, Owner::INITIATE)?;
+    require!(!multi_sig.paused || is_recovery(&instructions, &multi_sig.key()), MultiSigError::MultiSigPaused);
+
+    // a proposal must be able to run at least once the timelock has passed
+    let now = Clock::get()?.unix_timestamp;
+    if let Some(expires_at) = expires_at {
+        require!(expires_at > now + multi_sig.timelock_seconds, MultiSigError::InvalidExpiry);
+    }
+    // instructions must be readable by execute_transaction
+    if transfer.is_none() {
+        ProposalPayload::decode(&instructions)?;
+    }
+
+    let proposal_id = multi_sig.next_proposal_id;
+    multi_sig.next_proposal_id += 1;
+
+    proposal.multi_sig = multi_sig.key();
+    proposal.proposer = proposer;
+    proposal.instructions = instructions;
+    proposal.approvers = vec![];
+    proposal.rejecters = vec![];
+    proposal.status = ProposalStatus::Active;
+    proposal.ready = false;
+    proposal.created_at = now;
+    proposal.approved_at = None;
+    proposal.expires_at = expires_at;
+    proposal.proposal_id = proposal_id;
+    proposal.bump = bump;
+    proposal.transfer = transfer;
+    proposal.config_version = multi_sig.config_version;
+    proposal.approval_epoch = 0;
+    Ok(())
+}
+
+// report the proposal once its approvals carry enough weight
+fn check_ready(multi_sig: &Account<MultiSig>, proposal: &mut TransactionProposal, proposal_id: u64) -> Result<()> {
+    let approved_weight = multi_sig.weight_of(&proposal.approvers);
+    if !proposal.ready && approved_weight >= multi_sig.threshold {
+        proposal.ready = true;
+        // the timelock starts once the threshold is reached
+        proposal.approved_at = Some(Clock::get()?.unix_timestamp);
+        emit!(ProposalReady {
+            multi_sig: multi_sig.key(),
+            proposal_id,
+            approved_weight,
+        });
+    }
+    Ok(())
+}
+
+// an owner may execute an active proposal once it has enough approval weight,
+// its timelock has passed and it has not expired
+fn check_executable(multi_sig: &MultiSig, proposal: &TransactionProposal, executor: &Pubkey) -> Result<()> {
+    multi_sig.require_permission(executor, Owner::EXECUTE)?;
+    proposal.require_active()?;
+    proposal.require_current(multi_sig)?;
+    require!(
+        !multi_sig.paused || (proposal.transfer.is_none() && is_recovery(&proposal.instructions, &proposal.multi_sig)),
+        MultiSigError::MultiSigPaused
+    );
+    // the threshold may have changed since the last approval, so count again
+    require!(multi_sig.weight_of(&proposal.approvers) >= multi_sig.threshold, MultiSigError::NotEnoughApprovals);
+
+    // owners get timelock_seconds to react to an approved proposal, and
+    // expired proposals cannot run at all
+    let now = Clock::get()?.unix_timestamp;
+    if multi_sig.timelock_seconds > 0 {
+        let approved_at = proposal.approved_at.ok_or(MultiSigError::TimelockNotElapsed)?;
+        require!(now >= approved_at + multi_sig.timelock_seconds, MultiSigError::TimelockNotElapsed);
+    }
+    if let Some(expires_at) = proposal.expires_at {
+        require!(now < expires_at, MultiSigError::ProposalExpired);
+    }
+    Ok(())
+}
+
+// the accounts needed to pay SOL or tokens out of a multisig's vault
+struct Payout<'a, 'info> {
+    multi_sig: Pubkey,
+    vault: &'a SystemAccount<'info>,
+    vault_bump: u8,
+    recipient: AccountInfo<'info>,
+    vault_token_account: Option<&'a Account<'info, TokenAccount>>,
+    recipient_token_account: Option<&'a Account<'info, TokenAccount>>,
+    token_program: Option<&'a Program<'info, Token>>,
+    system_program: &'a Program<'info, System>,
+}
+
+impl Payout<'_, '_> {
+    fn pay(&self, transfer: &TransferProposal) -> Result<()> {
+        require_keys_eq!(self.recipient.key(), transfer.recipient, MultiSigError::AccountMismatch);
+        let vault_seeds: &[&[u8]] = &[b"vault", self.multi_sig.as_ref(), &[self.vault_bump]];
+
+        match transfer.mint {
+            // SOL straight out of the vault
+            None => system_program::transfer(
+                CpiContext::new_with_signer(
+                    self.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: self.vault.to_account_info(),
+                        to: self.recipient.clone(),
+                    },
+                    &[vault_seeds],
+                ),
+                transfer.amount,
+            ),
+            // tokens from a vault token account to one of the recipient's
+            Some(mint) => {
+                let from = self.vault_token_account.ok_or(MultiSigError::AccountMismatch)?;
+                let to = self.recipient_token_account.ok_or(MultiSigError::AccountMismatch)?;
+                let token_program = self.token_program.ok_or(MultiSigError::AccountMismatch)?;
+                require!(from.mint == mint && from.owner == self.vault.key(), MultiSigError::AccountMismatch);
+                require!(to.mint == mint && to.owner == transfer.recipient, MultiSigError::AccountMismatch);
+
+                token::transfer(
+                    CpiContext::new_with_signer(
+                        token_program.to_account_info(),
+                        token::Transfer {
+                            from: from.to_account_info(),
+                            to: to.to_account_info(),
+                            authority: self.vault.to_account_info(),
+                        },
+                        &[vault_seeds],
+                    ),
+                    transfer.amount,
+                )
+            }
+        }
+    }
+}
+
+// whether a payload only unpauses `multi_sig`, the proposals a paused multisig
+// still accepts
+fn is_recovery(instructions: &[u8], multi_sig: &Pubkey) -> bool {
+    let Ok(payload) = ProposalPayload::decode(instructions) else {
+        return false;
+    };
+    !payload.instructions.is_empty()
+        && payload.instructions.iter().all(|ix| {
+            ix.program_id == crate::ID
+                && ix.accounts.first().is_some_and(|meta| meta.pubkey == *multi_sig)
+                && ix.data.starts_with(instruction::Unpause::DISCRIMINATOR)
+        })
+}
+
+// the owners a multisig is created with must be unique and fit its account
+fn validate_owners(owners: &[Owner]) -> Result<()> {
+    require!(owners.len() <= MAX_OWNERS, MultiSigError::TooManyOwners);
+    for (i, owner) in owners.iter().enumerate() {
+        require!(owner.weight > 0, MultiSigError::InvalidWeight);
+        require!(valid_permissions(owner.permissions), MultiSigError::InvalidPermissions);
+        require!(!owners[i + 1..].iter().any(|o| o.key == owner.key), MultiSigError::DuplicateOwner);
+    }
+    require_operable(owners)
+}
+
+// every owner needs at least one permission, and only the known ones
+fn valid_permissions(permissions: u8) -> bool {
+    permissions != 0 && permissions & !Owner::ALL == 0
+}
+
+// someone must be left to propose and to execute, or the multisig is stuck
+fn require_operable(owners: &[Owner]) -> Result<()> {
+    require!(
+        owners.iter().any(|o| o.can(Owner::INITIATE)) && owners.iter().any(|o| o.can(Owner::EXECUTE)),
+        MultiSigError::InoperableOwnerSet
+    );
+    Ok(())
 }
 
 #[derive(Accounts)]
@@ -92,51 +731,534 @@ pub struct Initialize {}
 #[account]
 pub struct MultiSig {
     pub name: String,
-    pub owners: Vec<Pubkey>,
-    pub threshold: u8,
+    pub owners: Vec<Owner>,
+    pub threshold: u16, // Total weight of voting owners needed to approve a proposal
     pub next_proposal_id: u64,
+    pub timelock_seconds: i64, // Delay between reaching the threshold and execution
+    pub config_version: u32, // Bumped whenever the owners, the threshold or the timelock change
+    pub guardians: Vec<Pubkey>, // Keys that can pause the multisig and veto proposals
+    pub paused: bool, // Set by a guardian, blocks everything but recovery proposals
+}
+
+impl MultiSig {
+    // discriminator + name + owners (up to MAX_OWNERS) + threshold + next_proposal_id
+    // + timelock_seconds + config_version + guardians (up to MAX_GUARDIANS) + paused
+    pub fn space(name: &str) -> usize {
+        8 + 4 + name.len() + 4 + Owner::SIZE * MAX_OWNERS + 2 + 8 + 8 + 4 + 4 + 32 * MAX_GUARDIANS + 1
+    }
+
+    pub fn is_owner(&self, key: &Pubkey) -> bool {
+        self.owners.iter().any(|o| o.key == *key)
+    }
+
+    pub fn has_permission(&self, key: &Pubkey, permission: u8) -> bool {
+        self.owners.iter().any(|o| o.key == *key && o.can(permission))
+    }
+
+    pub fn require_permission(&self, key: &Pubkey, permission: u8) -> Result<()> {
+        require!(self.is_owner(key), MultiSigError::NotAnOwner);
+        require!(self.has_permission(key, permission), MultiSigError::MissingPermission);
+        Ok(())
+    }
+
+    // the weight the threshold is measured against, of owners who can vote
+    pub fn total_weight(&self) -> u16 {
+        self.owners.iter().map(Owner::voting_weight).sum()
+    }
+
+    // combined voting weight of the keys that are (still) owners
+    pub fn weight_of(&self, keys: &[Pubkey]) -> u16 {
+        self.owners
+            .iter()
+            .filter(|o| keys.contains(&o.key))
+            .map(Owner::voting_weight)
+            .sum()
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct Owner {
+    pub key: Pubkey, // A wallet, or the vault of another multisig voting through its own proposals
+    pub weight: u8, // Voting weight, 1 for every owner of an equal-weight multisig
+    pub permissions: u8, // Bitmask of Owner::INITIATE, Owner::VOTE and Owner::EXECUTE
+}
+
+impl Owner {
+    pub const SIZE: usize = 32 + 1 + 1;
+
+    // permission bits
+    pub const INITIATE: u8 = 1 << 0; // Create proposals
+    pub const VOTE: u8 = 1 << 1; // Approve or reject proposals, counted towards the threshold
+    pub const EXECUTE: u8 = 1 << 2; // Execute approved proposals
+    pub const ALL: u8 = Owner::INITIATE | Owner::VOTE | Owner::EXECUTE;
+
+    pub fn can(&self, permission: u8) -> bool {
+        self.permissions & permission == permission
+    }
+
+    pub fn voting_weight(&self) -> u16 {
+        if self.can(Owner::VOTE) {
+            self.weight as u16
+        } else {
+            0
+        }
+    }
 }
 
 #[account]
 pub struct TransactionProposal {
     pub multi_sig: Pubkey, // The multisig account this proposal belongs to
     pub proposer: Pubkey, // The owner who created the proposal
-    pub instructions: Vec<u8>, // The instructions to be executed
+    pub instructions: Vec<u8>, // The instructions to be executed, an encoded ProposalPayload
     pub approvers: Vec<Pubkey>, // Owners who have approved the proposal
-    pub executed: bool, // Whether the proposal has been executed
+    pub rejecters: Vec<Pubkey>, // Owners who have rejected the proposal
+    pub status: ProposalStatus, // Where the proposal is in its lifecycle
     pub created_at: i64, // Timestamp of creation
     pub proposal_id: u64, // Unique identifier for the proposal
+    pub bump: u8, // Bump of the proposal PDA
+    pub ready: bool, // Whether enough owners have approved the proposal
+    pub approved_at: Option<i64>, // When the threshold was reached
+    pub expires_at: Option<i64>, // After this the proposal can no longer execute
+    pub transfer: Option<TransferProposal>, // Set for payouts run by execute_transfer
+    pub config_version: u32, // The multisig's config_version when the proposal was created
+    pub approval_epoch: u32, // Bumped by every revoked approval, part of the offline approval message
+}
+
+impl TransactionProposal {
+    // discriminator + multi_sig + proposer + instructions + approvers and rejecters
+    // (up to MAX_OWNERS each) + status + created_at + proposal_id + bump + ready
+    // + approved_at + expires_at + transfer + config_version + approval_epoch
+    pub fn space(instructions_len: usize) -> usize {
+        8 + 32 + 32 + 4 + instructions_len + 2 * (4 + 32 * MAX_OWNERS) + 1 + 8 + 8 + 1 + 1 + 9 + 9
+            + 1 + TransferProposal::SIZE + 4 + 4
+    }
+
+    // votes and execution are only possible while the proposal is active
+    pub fn require_active(&self) -> Result<()> {
+        match self.status {
+            ProposalStatus::Active => Ok(()),
+            ProposalStatus::Executed => err!(MultiSigError::AlreadyExecuted),
+            ProposalStatus::Rejected => err!(MultiSigError::ProposalRejected),
+            ProposalStatus::Cancelled => err!(MultiSigError::ProposalCancelled),
+            ProposalStatus::Vetoed => err!(MultiSigError::ProposalVetoed),
+        }
+    }
+
+    // what owners sign to approve the proposal offline: SHA-256 of its
+    // instructions followed by its Borsh encoded transfer
+    pub fn hash(&self) -> [u8; 32] {
+        let transfer = borsh::to_vec(&self.transfer).expect("a transfer always serializes");
+        hashv(&[&self.instructions, &transfer]).to_bytes()
+    }
+
+    // executed, rejected, cancelled and stale proposals can never run again
+    pub fn closable(&self, multi_sig: &MultiSig) -> bool {
+        self.status != ProposalStatus::Active || self.config_version != multi_sig.config_version
+    }
+
+    // approvals collected under an older owner set or threshold no longer count
+    pub fn require_current(&self, multi_sig: &MultiSig) -> Result<()> {
+        require!(self.config_version == multi_sig.config_version, MultiSigError::StaleProposal);
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ProposalStatus {
+    Active, // Collecting approvals
+    Executed, // Its instructions have run
+    Rejected, // Too many owners rejected it to reach the threshold
+    Cancelled, // Withdrawn by the proposer
+    Vetoed, // Stopped by a guardian
+}
+
+// a payout from the vault, of SOL when `mint` is None
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct TransferProposal {
+    pub mint: Option<Pubkey>,
+    pub amount: u64,
+    pub recipient: Pubkey, // The wallet paid, for tokens the owner of the receiving token account
+}
+
+impl TransferProposal {
+    pub const SIZE: usize = 33 + 8 + 32;
+}
+
+// a proposal's instructions payload being written in chunks
+#[account]
+pub struct ProposalBuffer {
+    pub multi_sig: Pubkey, // The multisig the proposal will belong to
+    pub proposer: Pubkey, // The owner writing the buffer
+    pub final_hash: [u8; 32], // SHA-256 of the complete payload
+    pub data: Vec<u8>, // The payload, allocated at its final size
+    pub bump: u8, // Bump of the buffer PDA
+}
+
+impl ProposalBuffer {
+    // discriminator + multi_sig + proposer + final_hash + data + bump
+    pub fn space(final_size: u32) -> usize {
+        8 + 32 + 32 + 32 + 4 + final_size as usize + 1
+    }
+}
+
+// an allowance members can spend from the vault without a proposal
+#[account]
+pub struct SpendingLimit {
+    pub multi_sig: Pubkey, // The multisig whose vault pays out
+    pub limit_id: u64, // Identifies the limit among the multisig's limits
+    pub mint: Option<Pubkey>, // The token spent, SOL when None
+    pub amount: u64, // Allowance per period
+    pub period_seconds: i64, // Length of a period
+    pub remaining: u64, // What is left of the allowance in the current period
+    pub last_reset: i64, // Start of the current period
+    pub members: Vec<Pubkey>, // Owners allowed to spend
+    pub destinations: Vec<Pubkey>, // Allowed recipients, any recipient when empty
+    pub bump: u8, // Bump of the spending limit PDA
+}
+
+impl SpendingLimit {
+    // discriminator + multi_sig + limit_id + mint + amount + period_seconds + remaining
+    // + last_reset + members + destinations + bump
+    pub const SIZE: usize = 8 + 32 + 8 + 33 + 8 + 8 + 8 + 8
+        + (4 + 32 * MAX_OWNERS) + (4 + 32 * MAX_LIMIT_DESTINATIONS) + 1;
+
+    // refill the allowance once a period has passed, keeping periods aligned
+    // to when the limit was created
+    pub fn reset_if_due(&mut self, now: i64) {
+        let elapsed = now - self.last_reset;
+        if elapsed >= self.period_seconds {
+            self.last_reset += elapsed - elapsed % self.period_seconds;
+            self.remaining = self.amount;
+        }
+    }
+}
+
+#[event]
+pub struct ProposalReady {
+    pub multi_sig: Pubkey,
+    pub proposal_id: u64,
+    pub approved_weight: u16,
+}
+
+#[event]
+pub struct ProposalRejected {
+    pub multi_sig: Pubkey,
+    pub proposal_id: u64,
+    pub rejected_weight: u16,
+}
+
+#[event]
+pub struct ProposalExecuted {
+    pub multi_sig: Pubkey,
+    pub proposal_id: u64,
+    pub executor: Pubkey,
+}
+
+#[event]
+pub struct MultiSigPaused {
+    pub multi_sig: Pubkey,
+    pub guardian: Pubkey,
+}
+
+#[event]
+pub struct ProposalVetoed {
+    pub multi_sig: Pubkey,
+    pub proposal_id: u64,
+    pub guardian: Pubkey,
+}
+
+#[event]
+pub struct SpendingLimitUsed {
+    pub multi_sig: Pubkey,
+    pub limit_id: u64,
+    pub member: Pubkey,
+    pub amount: u64,
+    pub remaining: u64,
 }
 
 #[derive(Accounts)]
+#[instruction(instructions: Vec<u8>)]
 pub struct CreateProposal<'info> {
+    #[account(mut)]
+    pub multi_sig: Account<'info, MultiSig>,
     #[account(
         init, 
         payer = proposer, 
-        space = 8 + 32 + 32 + 4 + (instructions.len()),
-        seeds = [b"proposal", multi_sig.key().as_ref(), &[proposal_id as u8] ],
+        space = TransactionProposal::space(instructions.len()),
+        seeds = [b"proposal", multi_sig.key().as_ref(), &multi_sig.next_proposal_id.to_le_bytes()],
+        bump
+    )]
+    pub proposal: Account<'info, TransactionProposal>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeTransfer<'info> {
+    #[account(mut)]
+    pub multi_sig: Account<'info, MultiSig>,
+    #[account(
+        init,
+        payer = proposer,
+        space = TransactionProposal::space(0),
+        seeds = [b"proposal", multi_sig.key().as_ref(), &multi_sig.next_proposal_id.to_le_bytes()],
+        bump
+    )]
+    pub proposal: Account<'info, TransactionProposal>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(final_size: u32)]
+pub struct CreateProposalBuffer<'info> {
+    pub multi_sig: Account<'info, MultiSig>,
+    #[account(
+        init,
+        payer = proposer,
+        space = ProposalBuffer::space(final_size),
+        seeds = [b"proposal-buffer", multi_sig.key().as_ref(), proposer.key().as_ref()],
         bump
     )]
+    pub buffer: Account<'info, ProposalBuffer>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AppendToBuffer<'info> {
+    pub multi_sig: Account<'info, MultiSig>,
+    #[account(
+        mut,
+        has_one = multi_sig,
+        has_one = proposer,
+        seeds = [b"proposal-buffer", multi_sig.key().as_ref(), proposer.key().as_ref()],
+        bump = buffer.bump
+    )]
+    pub buffer: Account<'info, ProposalBuffer>,
+    pub proposer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeProposal<'info> {
+    #[account(mut)]
     pub multi_sig: Account<'info, MultiSig>,
+    #[account(
+        mut,
+        close = proposer,
+        has_one = multi_sig,
+        has_one = proposer,
+        seeds = [b"proposal-buffer", multi_sig.key().as_ref(), proposer.key().as_ref()],
+        bump = buffer.bump
+    )]
+    pub buffer: Account<'info, ProposalBuffer>,
+    #[account(
+        init,
+        payer = proposer,
+        space = TransactionProposal::space(buffer.data.len()),
+        seeds = [b"proposal", multi_sig.key().as_ref(), &multi_sig.next_proposal_id.to_le_bytes()],
+        bump
+    )]
+    pub proposal: Account<'info, TransactionProposal>,
+    #[account(mut)]
     pub proposer: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct CloseProposalBuffer<'info> {
+    pub multi_sig: Account<'info, MultiSig>,
+    #[account(
+        mut,
+        close = proposer,
+        has_one = multi_sig,
+        has_one = proposer,
+        seeds = [b"proposal-buffer", multi_sig.key().as_ref(), proposer.key().as_ref()],
+        bump = buffer.bump
+    )]
+    pub buffer: Account<'info, ProposalBuffer>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+}
+
 #[derive(Accounts)]
 #[instruction(proposal_id: u64)]
 pub struct ApproveTransaction<'info> {
-    #[account(mut, seeds = [b"proposal", multi_sig.key().as_ref(), &[proposal_id as u8] ], proposal.bump)]
+    pub multi_sig: Account<'info, MultiSig>,
+    #[account(mut, has_one = multi_sig, seeds = [b"proposal", multi_sig.key().as_ref(), &proposal_id.to_le_bytes()], bump = proposal.bump)]
+    pub proposal: Account<'info, TransactionProposal>,
+    pub approver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct ApproveWithSignatures<'info> {
+    pub multi_sig: Account<'info, MultiSig>,
+    #[account(mut, has_one = multi_sig, seeds = [b"proposal", multi_sig.key().as_ref(), &proposal_id.to_le_bytes()], bump = proposal.bump)]
+    pub proposal: Account<'info, TransactionProposal>,
+    /// CHECK: the instructions sysvar, checked by address
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct RejectTransaction<'info> {
+    pub multi_sig: Account<'info, MultiSig>,
+    #[account(mut, has_one = multi_sig, seeds = [b"proposal", multi_sig.key().as_ref(), &proposal_id.to_le_bytes()], bump = proposal.bump)]
+    pub proposal: Account<'info, TransactionProposal>,
+    pub rejecter: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct RevokeApproval<'info> {
+    pub multi_sig: Account<'info, MultiSig>,
+    #[account(mut, has_one = multi_sig, seeds = [b"proposal", multi_sig.key().as_ref(), &proposal_id.to_le_bytes()], bump = proposal.bump)]
     pub proposal: Account<'info, TransactionProposal>,
     pub approver: Signer<'info>,
 }
 
 #[derive(Accounts)]
-#[instruction(name: String, nonce: u8, owners: Vec<Pubkey>, threshold: u8)]
+#[instruction(proposal_id: u64)]
+pub struct CancelProposal<'info> {
+    pub multi_sig: Account<'info, MultiSig>,
+    #[account(mut, has_one = multi_sig, seeds = [b"proposal", multi_sig.key().as_ref(), &proposal_id.to_le_bytes()], bump = proposal.bump)]
+    pub proposal: Account<'info, TransactionProposal>,
+    pub proposer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct ExecuteTransaction<'info> {
+    pub multi_sig: Account<'info, MultiSig>,
+    #[account(mut, has_one = multi_sig, seeds = [b"proposal", multi_sig.key().as_ref(), &proposal_id.to_le_bytes()], bump = proposal.bump)]
+    pub proposal: Account<'info, TransactionProposal>,
+    /// the vault PDA that signs the proposal's instructions
+    #[account(seeds = [b"vault", multi_sig.key().as_ref()], bump)]
+    pub vault: SystemAccount<'info>,
+    pub executor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct ExecuteTransfer<'info> {
+    pub multi_sig: Account<'info, MultiSig>,
+    #[account(mut, has_one = multi_sig, seeds = [b"proposal", multi_sig.key().as_ref(), &proposal_id.to_le_bytes()], bump = proposal.bump)]
+    pub proposal: Account<'info, TransactionProposal>,
+    /// the vault PDA paying out SOL or owning the token account paying out tokens
+    #[account(mut, seeds = [b"vault", multi_sig.key().as_ref()], bump)]
+    pub vault: SystemAccount<'info>,
+    /// CHECK: must be the recipient named by the proposal
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+    /// only for token transfers
+    #[account(mut)]
+    pub vault_token_account: Option<Account<'info, TokenAccount>>,
+    /// only for token transfers
+    #[account(mut)]
+    pub recipient_token_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Option<Program<'info, Token>>,
+    pub executor: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct CloseProposal<'info> {
+    pub multi_sig: Account<'info, MultiSig>,
+    #[account(
+        mut,
+        close = proposer,
+        has_one = multi_sig,
+        has_one = proposer,
+        seeds = [b"proposal", multi_sig.key().as_ref(), &proposal_id.to_le_bytes()],
+        bump = proposal.bump
+    )]
+    pub proposal: Account<'info, TransactionProposal>,
+    /// receives the proposal's rent
+    #[account(mut)]
+    pub proposer: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseProposals<'info> {
+    pub multi_sig: Account<'info, MultiSig>,
+}
+
+#[derive(Accounts)]
+#[instruction(limit_id: u64)]
+pub struct AddSpendingLimit<'info> {
+    pub multi_sig: Account<'info, MultiSig>,
+    #[account(
+        init,
+        payer = vault,
+        space = SpendingLimit::SIZE,
+        seeds = [b"spending-limit", multi_sig.key().as_ref(), &limit_id.to_le_bytes()],
+        bump
+    )]
+    pub spending_limit: Account<'info, SpendingLimit>,
+    /// the multisig's vault, which only signs from an executed proposal
+    #[account(mut, seeds = [b"vault", multi_sig.key().as_ref()], bump)]
+    pub vault: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(limit_id: u64)]
+pub struct RemoveSpendingLimit<'info> {
+    pub multi_sig: Account<'info, MultiSig>,
+    #[account(
+        mut,
+        close = vault,
+        has_one = multi_sig,
+        seeds = [b"spending-limit", multi_sig.key().as_ref(), &limit_id.to_le_bytes()],
+        bump = spending_limit.bump
+    )]
+    pub spending_limit: Account<'info, SpendingLimit>,
+    /// the multisig's vault, which only signs from an executed proposal
+    #[account(mut, seeds = [b"vault", multi_sig.key().as_ref()], bump)]
+    pub vault: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(limit_id: u64)]
+pub struct Spend<'info> {
+    pub multi_sig: Account<'info, MultiSig>,
+    #[account(
+        mut,
+        has_one = multi_sig,
+        seeds = [b"spending-limit", multi_sig.key().as_ref(), &limit_id.to_le_bytes()],
+        bump = spending_limit.bump
+    )]
+    pub spending_limit: Account<'info, SpendingLimit>,
+    /// the vault PDA paying out SOL or owning the token account paying out tokens
+    #[account(mut, seeds = [b"vault", multi_sig.key().as_ref()], bump)]
+    pub vault: SystemAccount<'info>,
+    /// CHECK: checked against the limit's destinations
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+    /// only for token limits
+    #[account(mut)]
+    pub vault_token_account: Option<Account<'info, TokenAccount>>,
+    /// only for token limits
+    #[account(mut)]
+    pub recipient_token_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Option<Program<'info, Token>>,
+    pub member: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String, nonce: u8)]
 pub struct CreateMultiSig<'info> {    
     #[account(
         init, 
         payer = user, 
-        space = 8 + 32 + 4 + (32 * 10) + 1, 
+        space = MultiSig::space(&name),
         seeds = [b"multi-sig", user.key().as_ref(), name.as_bytes(), &[nonce] ], 
         bump
     )]
@@ -150,26 +1272,165 @@ pub struct CreateMultiSig<'info> {
 pub struct AddOwner<'info>{
     #[account(mut)]
     pub multi_sig: Account<'info, MultiSig>,
-    pub user: Signer<'info>,
+    /// the multisig's vault, which only signs from an executed proposal
+    #[account(seeds = [b"vault", multi_sig.key().as_ref()], bump)]
+    pub vault: Signer<'info>,
 }
 
 #[derive(Accounts)]
 pub struct RemoveOwner<'info>{
     #[account(mut)]
     pub multi_sig: Account<'info, MultiSig>,
-    pub user: Signer<'info>,
+    /// the multisig's vault, which only signs from an executed proposal
+    #[account(seeds = [b"vault", multi_sig.key().as_ref()], bump)]
+    pub vault: Signer<'info>,
+}
+#[derive(Accounts)]
+pub struct UpdateTimelock<'info>{
+    #[account(mut)]
+    pub multi_sig: Account<'info, MultiSig>,
+    /// the multisig's vault, which only signs from an executed proposal
+    #[account(seeds = [b"vault", multi_sig.key().as_ref()], bump)]
+    pub vault: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetGuardians<'info> {
+    #[account(mut)]
+    pub multi_sig: Account<'info, MultiSig>,
+    /// the multisig's vault, which only signs from an executed proposal
+    #[account(seeds = [b"vault", multi_sig.key().as_ref()], bump)]
+    pub vault: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Pause<'info> {
+    #[account(mut)]
+    pub multi_sig: Account<'info, MultiSig>,
+    pub guardian: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Unpause<'info> {
+    #[account(mut)]
+    pub multi_sig: Account<'info, MultiSig>,
+    /// the multisig's vault, which only signs from an executed proposal
+    #[account(seeds = [b"vault", multi_sig.key().as_ref()], bump)]
+    pub vault: Signer<'info>,
 }
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct Veto<'info> {
+    pub multi_sig: Account<'info, MultiSig>,
+    #[account(mut, has_one = multi_sig, seeds = [b"proposal", multi_sig.key().as_ref(), &proposal_id.to_le_bytes()], bump = proposal.bump)]
+    pub proposal: Account<'info, TransactionProposal>,
+    pub guardian: Signer<'info>,
+}
+
 #[derive(Accounts)]
 pub struct UpdateThreshold<'info>{
     #[account(mut)]
     pub multi_sig: Account<'info, MultiSig>,
-    pub user: Signer<'info>,
+    /// the multisig's vault, which only signs from an executed proposal
+    #[account(seeds = [b"vault", multi_sig.key().as_ref()], bump)]
+    pub vault: Signer<'info>,
 }
 
 #[error_code]
 pub enum MultiSigError {
-    #[msg("The threshold cannot be greater than the number of owners.")]
+    #[msg("The threshold cannot be greater than the total weight of the voting owners.")]
     InvalidThreshold,
     #[msg("The threshold must be greater than zero.")]
     InvalidThresholdGtZero,
+    #[msg("The signer is not an owner of this multisig.")]
+    NotAnOwner,
+    #[msg("The owner is already part of this multisig.")]
+    DuplicateOwner,
+    #[msg("The multisig cannot hold more owners.")]
+    TooManyOwners,
+    #[msg("The owner is not part of this multisig.")]
+    OwnerNotFound,
+    #[msg("Removing the owner would leave less weight than the threshold.")]
+    WouldBreakThreshold,
+    #[msg("Owner weights must be greater than zero.")]
+    InvalidWeight,
+    #[msg("Owner permissions must be a non-empty combination of Initiate, Vote and Execute.")]
+    InvalidPermissions,
+    #[msg("The owners must include someone who can initiate and someone who can execute proposals.")]
+    InoperableOwnerSet,
+    #[msg("The owner does not have the permission for this action.")]
+    MissingPermission,
+    #[msg("The owner has already approved this proposal.")]
+    AlreadyApproved,
+    #[msg("The proposal has already been executed.")]
+    AlreadyExecuted,
+    #[msg("The owner has already rejected this proposal.")]
+    AlreadyRejected,
+    #[msg("The owner has not approved this proposal.")]
+    NotApproved,
+    #[msg("Only the proposer can cancel the proposal.")]
+    NotProposer,
+    #[msg("The proposal has been rejected.")]
+    ProposalRejected,
+    #[msg("The proposal has been cancelled.")]
+    ProposalCancelled,
+    #[msg("The proposal has been vetoed by a guardian.")]
+    ProposalVetoed,
+    #[msg("The multisig is paused.")]
+    MultiSigPaused,
+    #[msg("The signer is not a guardian of this multisig.")]
+    NotAGuardian,
+    #[msg("The multisig cannot hold more guardians.")]
+    TooManyGuardians,
+    #[msg("A proposal that unpauses the multisig cannot be vetoed.")]
+    RecoveryProposal,
+    #[msg("A proposal can only be vetoed while its timelock runs.")]
+    NotInTimelock,
+    #[msg("The timelock must be between zero and MAX_TIMELOCK_SECONDS.")]
+    InvalidTimelock,
+    #[msg("The proposal would expire before it could be executed.")]
+    InvalidExpiry,
+    #[msg("The timelock of the proposal has not elapsed yet.")]
+    TimelockNotElapsed,
+    #[msg("The proposal has expired.")]
+    ProposalExpired,
+    #[msg("The owners or threshold have changed since the proposal was created.")]
+    StaleProposal,
+    #[msg("The proposal is still active and not stale.")]
+    ProposalStillActive,
+    #[msg("The transfer amount must be greater than zero.")]
+    InvalidAmount,
+    #[msg("The proposal must be executed with a different instruction.")]
+    WrongProposalKind,
+    #[msg("The proposal buffer exceeds MAX_PROPOSAL_BUFFER_SIZE.")]
+    BufferTooLarge,
+    #[msg("The chunk does not fit in the proposal buffer.")]
+    ChunkOutOfBounds,
+    #[msg("The proposal buffer does not match its declared hash.")]
+    BufferHashMismatch,
+    #[msg("The spending limit period must be greater than zero.")]
+    InvalidPeriod,
+    #[msg("A spending limit needs between one and MAX_OWNERS members.")]
+    InvalidLimitMembers,
+    #[msg("The spending limit allows too many destinations.")]
+    TooManyDestinations,
+    #[msg("The signer is not a member of this spending limit.")]
+    NotALimitMember,
+    #[msg("The spending limit does not allow this destination.")]
+    DestinationNotAllowed,
+    #[msg("The amount exceeds what is left of the spending limit.")]
+    SpendingLimitExceeded,
+    #[msg("An Ed25519 program instruction could not be read.")]
+    InvalidSignatureInstruction,
+    #[msg("No signature approved the proposal.")]
+    NoValidSignatures,
+    #[msg("The proposal payload has an unsupported version.")]
+    UnsupportedPayloadVersion,
+    #[msg("The proposal does not have enough approvals.")]
+    NotEnoughApprovals,
+    #[msg("The proposal instructions could not be decoded.")]
+    InvalidInstructionData,
+    #[msg("The supplied accounts do not match the proposal instructions.")]
+    AccountMismatch,
 }
\ No newline at end of file
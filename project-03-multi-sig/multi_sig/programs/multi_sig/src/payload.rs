@@ -0,0 +1,215 @@
+// the format of `TransactionProposal.instructions`, shared by the program and
+// off-chain tools: a version byte followed by the Borsh encoded instructions
+
+use std::fmt;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::system_program;
+use anchor_spl::token::spl_token;
+use anchor_spl::token::spl_token::instruction::TokenInstruction;
+
+use crate::MultiSigError;
+
+// bumped whenever the layout below changes
+pub const PAYLOAD_VERSION: u8 = 1;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ProposalPayload {
+    pub version: u8,
+    pub instructions: Vec<ProposalInstruction>,
+}
+
+// one instruction of a proposal, run with the vault's signature
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ProposalInstruction {
+    pub program_id: Pubkey,
+    pub accounts: Vec<ProposalAccountMeta>,
+    pub data: Vec<u8>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ProposalAccountMeta {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+impl ProposalPayload {
+    pub fn new(instructions: &[Instruction]) -> Self {
+        ProposalPayload {
+            version: PAYLOAD_VERSION,
+            instructions: instructions.iter().map(ProposalInstruction::from).collect(),
+        }
+    }
+
+    // the bytes to pass to create_transaction_proposal or a proposal buffer
+    pub fn encode(&self) -> Vec<u8> {
+        borsh::to_vec(self).expect("a payload always serializes")
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        // check the version first, later layouts need not parse as this one
+        match data.first() {
+            Some(&PAYLOAD_VERSION) => {}
+            Some(_) => return err!(MultiSigError::UnsupportedPayloadVersion),
+            None => return err!(MultiSigError::InvalidInstructionData),
+        }
+        Self::try_from_slice(data).map_err(|_| error!(MultiSigError::InvalidInstructionData))
+    }
+}
+
+impl From<&Instruction> for ProposalInstruction {
+    fn from(ix: &Instruction) -> Self {
+        ProposalInstruction {
+            program_id: ix.program_id,
+            accounts: ix
+                .accounts
+                .iter()
+                .map(|meta| ProposalAccountMeta {
+                    pubkey: meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: ix.data.clone(),
+        }
+    }
+}
+
+impl From<&ProposalInstruction> for Instruction {
+    fn from(ix: &ProposalInstruction) -> Self {
+        Instruction {
+            program_id: ix.program_id,
+            accounts: ix
+                .accounts
+                .iter()
+                .map(|meta| AccountMeta {
+                    pubkey: meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: ix.data.clone(),
+        }
+    }
+}
+
+// a listing of the payload for reviewers, with System and SPL Token
+// instructions decoded
+impl fmt::Display for ProposalPayload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "payload v{}, {} instruction(s)", self.version, self.instructions.len())?;
+        for (index, ix) in self.instructions.iter().enumerate() {
+            writeln!(f, "#{index} {}", ix.describe())?;
+            for meta in &ix.accounts {
+                let signer = if meta.is_signer { " signer" } else { "" };
+                let writable = if meta.is_writable { " writable" } else { "" };
+                writeln!(f, "    {}{signer}{writable}", meta.pubkey)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ProposalInstruction {
+    // a one line summary of what the instruction does
+    pub fn describe(&self) -> String {
+        if self.program_id == system_program::ID {
+            match describe_system(&self.data) {
+                Some(summary) => format!("System: {summary}"),
+                None => format!("System: unknown instruction ({} bytes)", self.data.len()),
+            }
+        } else if self.program_id == spl_token::ID {
+            match TokenInstruction::unpack(&self.data) {
+                Ok(instruction) => format!("SPL Token: {instruction:?}"),
+                Err(_) => format!("SPL Token: unknown instruction ({} bytes)", self.data.len()),
+            }
+        } else {
+            format!("{}: {} bytes of data", self.program_id, self.data.len())
+        }
+    }
+}
+
+// the System instructions proposals commonly use, bincode encoded as a u32
+// tag followed by their fields
+fn describe_system(data: &[u8]) -> Option<String> {
+    let (tag, rest) = data.split_first_chunk::<4>()?;
+    let u64_at = |at: usize| rest.get(at..at + 8).map(|b| u64::from_le_bytes(b.try_into().unwrap()));
+    let pubkey_at = |at: usize| rest.get(at..at + 32).map(|b| Pubkey::try_from(b).unwrap());
+
+    match u32::from_le_bytes(*tag) {
+        0 => Some(format!(
+            "create account with {} lamports and {} bytes owned by {}",
+            u64_at(0)?,
+            u64_at(8)?,
+            pubkey_at(16)?
+        )),
+        1 => Some(format!("assign to {}", pubkey_at(0)?)),
+        2 => Some(format!("transfer {} lamports", u64_at(0)?)),
+        8 => Some(format!("allocate {} bytes", u64_at(0)?)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transfer_payload() -> ProposalPayload {
+        let (from, to) = (Pubkey::new_unique(), Pubkey::new_unique());
+        ProposalPayload::new(&[solana_system_interface::instruction::transfer(&from, &to, 42)])
+    }
+
+    #[test]
+    fn round_trip() {
+        let (source, destination, authority) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let ix = spl_token::instruction::transfer(&spl_token::ID, &source, &destination, &authority, &[], 7).unwrap();
+
+        let bytes = ProposalPayload::new(std::slice::from_ref(&ix)).encode();
+        assert_eq!(bytes[0], PAYLOAD_VERSION);
+        let decoded = ProposalPayload::decode(&bytes).unwrap();
+        assert_eq!(decoded.version, PAYLOAD_VERSION);
+        assert_eq!(decoded.instructions.len(), 1);
+        assert_eq!(Instruction::from(&decoded.instructions[0]), ix);
+    }
+
+    #[test]
+    fn unknown_version_is_rejected() {
+        let mut bytes = transfer_payload().encode();
+        bytes[0] = PAYLOAD_VERSION + 1;
+        assert_eq!(
+            ProposalPayload::decode(&bytes).err(),
+            Some(error!(MultiSigError::UnsupportedPayloadVersion))
+        );
+        assert_eq!(ProposalPayload::decode(&[]).err(), Some(error!(MultiSigError::InvalidInstructionData)));
+    }
+
+    #[test]
+    fn describes_system_transfer() {
+        let payload = transfer_payload();
+        assert_eq!(payload.instructions[0].describe(), "System: transfer 42 lamports");
+
+        let listing = payload.to_string();
+        let from = payload.instructions[0].accounts[0].pubkey;
+        let to = payload.instructions[0].accounts[1].pubkey;
+        assert_eq!(
+            listing,
+            format!(
+                "payload v1, 1 instruction(s)\n#0 System: transfer 42 lamports\n    {from} signer writable\n    {to} writable\n"
+            )
+        );
+    }
+
+    #[test]
+    fn describes_token_transfer() {
+        let (source, destination, authority) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let ix = spl_token::instruction::transfer(&spl_token::ID, &source, &destination, &authority, &[], 7).unwrap();
+        let payload = ProposalPayload::new(&[ix]);
+        assert_eq!(payload.instructions[0].describe(), "SPL Token: Transfer { amount: 7 }");
+
+        let mut unknown = payload.instructions[0].clone();
+        unknown.data = vec![255];
+        assert_eq!(unknown.describe(), "SPL Token: unknown instruction (1 bytes)");
+    }
+}
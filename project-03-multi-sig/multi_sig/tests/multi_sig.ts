@@ -1,16 +1,1444 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { MultiSig } from "../target/types/multi_sig";
+import { assert } from "chai";
+import { createHash } from "crypto";
 
 describe("multi_sig", () => {
   // Configure the client to use the local cluster.
   anchor.setProvider(anchor.AnchorProvider.env());
 
   const program = anchor.workspace.multiSig as Program<MultiSig>;
+  const provider = anchor.getProvider() as anchor.AnchorProvider;
+
+  // owner permission bits
+  const INITIATE = 1;
+  const VOTE = 2;
+  const EXECUTE = 4;
+  const ALL = INITIATE | VOTE | EXECUTE;
+
+  const name = "treasury";
+  const nonce = 0;
+  const ownerB = anchor.web3.Keypair.generate();
+  const ownerC = anchor.web3.Keypair.generate();
+  const outsider = anchor.web3.Keypair.generate();
+
+  const [multiSigPDA] = anchor.web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("multi-sig"),
+      provider.wallet.publicKey.toBuffer(),
+      Buffer.from(name),
+      Buffer.from([nonce]),
+    ],
+    program.programId
+  );
+
+  const proposalPDA = (proposalId: number) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("proposal"),
+        multiSigPDA.toBuffer(),
+        new anchor.BN(proposalId).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+
+  const [vaultPDA] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("vault"), multiSigPDA.toBuffer()],
+    program.programId
+  );
+
+  // Borsh encoding of the ProposalPayload a proposal executes, a version byte
+  // followed by the instructions
+  const encodeInstructions = (
+    instructions: anchor.web3.TransactionInstruction[]
+  ) => {
+    const u32 = (value: number) => {
+      const buffer = Buffer.alloc(4);
+      buffer.writeUInt32LE(value);
+      return buffer;
+    };
+    const parts = [Buffer.from([1]), u32(instructions.length)];
+    for (const ix of instructions) {
+      parts.push(ix.programId.toBuffer(), u32(ix.keys.length));
+      for (const key of ix.keys) {
+        parts.push(
+          key.pubkey.toBuffer(),
+          Buffer.from([key.isSigner ? 1 : 0, key.isWritable ? 1 : 0])
+        );
+      }
+      parts.push(u32(ix.data.length), ix.data);
+    }
+    return Buffer.concat(parts);
+  };
+
+  before(async () => {
+    // fund the extra keys so they can pay for their own proposals
+    for (const key of [ownerB, ownerC, outsider]) {
+      const airdrop = await provider.connection.requestAirdrop(
+        key.publicKey,
+        anchor.web3.LAMPORTS_PER_SOL
+      );
+      await provider.connection.confirmTransaction(airdrop);
+    }
+  });
 
   it("Is initialized!", async () => {
     // Add your test here.
     const tx = await program.methods.initialize().rpc();
     console.log("Your transaction signature", tx);
   });
+
+  it("Create a multisig!", async () => {
+    await program.methods
+      .createMultiSig(
+        name,
+        nonce,
+        [provider.wallet.publicKey, ownerB.publicKey, ownerC.publicKey].map(
+          (key) => ({ key, weight: 1, permissions: ALL })
+        ),
+        2,
+        new anchor.BN(0)
+      )
+      .accounts({
+        multiSig: multiSigPDA,
+        user: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const multiSigAccount = await program.account.multiSig.fetch(multiSigPDA);
+    assert.equal(multiSigAccount.owners.length, 3);
+    assert.equal(multiSigAccount.threshold, 2);
+    assert.equal(multiSigAccount.nextProposalId.toNumber(), 0);
+  });
+
+  it("Create a transaction proposal!", async () => {
+    const instructions = encodeInstructions([]);
+
+    await program.methods
+      .createTransactionProposal(instructions, null)
+      .accounts({
+        multiSig: multiSigPDA,
+        proposal: proposalPDA(0),
+        proposer: ownerB.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([ownerB])
+      .rpc();
+
+    const proposal = await program.account.transactionProposal.fetch(
+      proposalPDA(0)
+    );
+    assert.ok(proposal.multiSig.equals(multiSigPDA));
+    assert.ok(proposal.proposer.equals(ownerB.publicKey));
+    assert.ok(Buffer.from(proposal.instructions).equals(instructions));
+    assert.equal(proposal.proposalId.toNumber(), 0);
+
+    const multiSigAccount = await program.account.multiSig.fetch(multiSigPDA);
+    assert.equal(multiSigAccount.nextProposalId.toNumber(), 1);
+  });
+
+  it("Reject payloads in an unknown format!", async () => {
+    for (const [payload, code] of [
+      [Buffer.from([2, 0, 0, 0, 0]), "UnsupportedPayloadVersion"],
+      [Buffer.from([1, 2, 3]), "InvalidInstructionData"],
+    ] as const) {
+      try {
+        await program.methods
+          .createTransactionProposal(payload, null)
+          .accounts({
+            multiSig: multiSigPDA,
+            proposal: proposalPDA(1),
+            proposer: provider.wallet.publicKey,
+            systemProgram: anchor.web3.SystemProgram.programId,
+          })
+          .rpc();
+        assert.fail("a malformed payload was accepted");
+      } catch (err) {
+        assert.equal(err.error.errorCode.code, code);
+      }
+    }
+  });
+
+  it("Reject proposals from non-owners!", async () => {
+    try {
+      await program.methods
+        .createTransactionProposal(Buffer.from([1]), null)
+        .accounts({
+          multiSig: multiSigPDA,
+          proposal: proposalPDA(1),
+          proposer: outsider.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([outsider])
+        .rpc();
+      assert.fail("a non-owner created a proposal");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "NotAnOwner");
+    }
+  });
+
+  it("Approve a transaction proposal!", async () => {
+    const approve = (approver: anchor.web3.Keypair) =>
+      program.methods
+        .approveTransaction(new anchor.BN(0))
+        .accounts({
+          multiSig: multiSigPDA,
+          proposal: proposalPDA(0),
+          approver: approver.publicKey,
+        })
+        .signers([approver])
+        .rpc();
+
+    await approve(ownerB);
+    let proposal = await program.account.transactionProposal.fetch(
+      proposalPDA(0)
+    );
+    assert.equal(proposal.approvers.length, 1);
+    assert.equal(proposal.ready, false);
+
+    try {
+      await approve(ownerB);
+      assert.fail("an owner approved twice");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "AlreadyApproved");
+    }
+
+    try {
+      await approve(outsider);
+      assert.fail("a non-owner approved");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "NotAnOwner");
+    }
+
+    // the second approval reaches the threshold of 2
+    await approve(ownerC);
+    proposal = await program.account.transactionProposal.fetch(proposalPDA(0));
+    assert.equal(proposal.approvers.length, 2);
+    assert.equal(proposal.ready, true);
+  });
+
+  it("Execute an approved proposal from the vault!", async () => {
+    const recipient = anchor.web3.Keypair.generate().publicKey;
+    const amount = anchor.web3.LAMPORTS_PER_SOL / 10;
+
+    // fund the vault, then propose paying the recipient out of it
+    const airdrop = await provider.connection.requestAirdrop(
+      vaultPDA,
+      anchor.web3.LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(airdrop);
+
+    const transfer = anchor.web3.SystemProgram.transfer({
+      fromPubkey: vaultPDA,
+      toPubkey: recipient,
+      lamports: amount,
+    });
+    await program.methods
+      .createTransactionProposal(encodeInstructions([transfer]), null)
+      .accounts({
+        multiSig: multiSigPDA,
+        proposal: proposalPDA(1),
+        proposer: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const execute = () =>
+      program.methods
+        .executeTransaction(new anchor.BN(1))
+        .accounts({
+          multiSig: multiSigPDA,
+          proposal: proposalPDA(1),
+          vault: vaultPDA,
+          executor: provider.wallet.publicKey,
+        })
+        .remainingAccounts([
+          {
+            pubkey: anchor.web3.SystemProgram.programId,
+            isSigner: false,
+            isWritable: false,
+          },
+          { pubkey: vaultPDA, isSigner: false, isWritable: true },
+          { pubkey: recipient, isSigner: false, isWritable: true },
+        ])
+        .rpc();
+
+    // one approval is not enough for a threshold of 2
+    await program.methods
+      .approveTransaction(new anchor.BN(1))
+      .accounts({
+        multiSig: multiSigPDA,
+        proposal: proposalPDA(1),
+        approver: provider.wallet.publicKey,
+      })
+      .rpc();
+    try {
+      await execute();
+      assert.fail("a proposal below the threshold was executed");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "NotEnoughApprovals");
+    }
+
+    await program.methods
+      .approveTransaction(new anchor.BN(1))
+      .accounts({
+        multiSig: multiSigPDA,
+        proposal: proposalPDA(1),
+        approver: ownerB.publicKey,
+      })
+      .signers([ownerB])
+      .rpc();
+    await execute();
+
+    const proposal = await program.account.transactionProposal.fetch(
+      proposalPDA(1)
+    );
+    assert.deepEqual(proposal.status, { executed: {} });
+    assert.equal(await provider.connection.getBalance(recipient), amount);
+  });
+
+  it("Change the owners only through a proposal!", async () => {
+    const ownerD = anchor.web3.Keypair.generate().publicKey;
+
+    // the vault PDA only signs through execute_transaction, and no other
+    // signer can stand in for it
+    const impostor = anchor.web3.Keypair.generate();
+    try {
+      await program.methods
+        .addOwner(ownerD, 1, ALL)
+        .accounts({ multiSig: multiSigPDA, vault: impostor.publicKey })
+        .signers([impostor])
+        .rpc();
+      assert.fail("add_owner ran without the vault's signature");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "ConstraintSeeds");
+    }
+
+    const addOwner = await program.methods
+      .addOwner(ownerD, 1, ALL)
+      .accounts({ multiSig: multiSigPDA, vault: vaultPDA })
+      .instruction();
+    await program.methods
+      .createTransactionProposal(encodeInstructions([addOwner]), null)
+      .accounts({
+        multiSig: multiSigPDA,
+        proposal: proposalPDA(2),
+        proposer: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    for (const approver of [ownerB, ownerC]) {
+      await program.methods
+        .approveTransaction(new anchor.BN(2))
+        .accounts({
+          multiSig: multiSigPDA,
+          proposal: proposalPDA(2),
+          approver: approver.publicKey,
+        })
+        .signers([approver])
+        .rpc();
+    }
+
+    await program.methods
+      .executeTransaction(new anchor.BN(2))
+      .accounts({
+        multiSig: multiSigPDA,
+        proposal: proposalPDA(2),
+        vault: vaultPDA,
+        executor: provider.wallet.publicKey,
+      })
+      .remainingAccounts([
+        { pubkey: program.programId, isSigner: false, isWritable: false },
+        { pubkey: multiSigPDA, isSigner: false, isWritable: true },
+        { pubkey: vaultPDA, isSigner: false, isWritable: false },
+      ])
+      .rpc();
+
+    const multiSigAccount = await program.account.multiSig.fetch(multiSigPDA);
+    assert.equal(multiSigAccount.owners.length, 4);
+    assert.ok(multiSigAccount.owners[3].key.equals(ownerD));
+    assert.equal(multiSigAccount.owners[3].weight, 1);
+  });
+
+  it("Invalidate proposals from an older owner set!", async () => {
+    // proposal 0 was created before ownerD was added
+    try {
+      await program.methods
+        .approveTransaction(new anchor.BN(0))
+        .accounts({
+          multiSig: multiSigPDA,
+          proposal: proposalPDA(0),
+          approver: ownerC.publicKey,
+        })
+        .signers([ownerC])
+        .rpc();
+      assert.fail("a stale proposal was approved");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "StaleProposal");
+    }
+    try {
+      await program.methods
+        .rejectTransaction(new anchor.BN(0))
+        .accounts({
+          multiSig: multiSigPDA,
+          proposal: proposalPDA(0),
+          rejecter: ownerC.publicKey,
+        })
+        .signers([ownerC])
+        .rpc();
+      assert.fail("a stale proposal was rejected");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "StaleProposal");
+    }
+
+    // closing it refunds the rent to the proposer
+    const balance = await provider.connection.getBalance(
+      provider.wallet.publicKey
+    );
+    await program.methods
+      .closeProposal(new anchor.BN(0))
+      .accounts({
+        multiSig: multiSigPDA,
+        proposal: proposalPDA(0),
+        proposer: provider.wallet.publicKey,
+      })
+      .rpc();
+    assert.isNull(await provider.connection.getAccountInfo(proposalPDA(0)));
+    assert.isAbove(
+      await provider.connection.getBalance(provider.wallet.publicKey),
+      balance
+    );
+  });
+
+  it("Reject invalid owner sets!", async () => {
+    type NewOwner = {
+      key: anchor.web3.PublicKey;
+      weight: number;
+      permissions: number;
+    };
+    const owner = (
+      key: anchor.web3.PublicKey,
+      weight = 1,
+      permissions = ALL
+    ): NewOwner => ({ key, weight, permissions });
+    const create = (owners: NewOwner[]) => {
+      const [pda] = anchor.web3.PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("multi-sig"),
+          provider.wallet.publicKey.toBuffer(),
+          Buffer.from("invalid"),
+          Buffer.from([nonce]),
+        ],
+        program.programId
+      );
+      return program.methods
+        .createMultiSig(
+          "invalid",
+          nonce,
+          owners,
+          1,
+          new anchor.BN(0)
+        )
+        .accounts({
+          multiSig: pda,
+          user: provider.wallet.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+    };
+
+    try {
+      await create([owner(ownerB.publicKey), owner(ownerB.publicKey)]);
+      assert.fail("a duplicate owner was accepted");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "DuplicateOwner");
+    }
+
+    const tooMany = Array.from({ length: 11 }, () =>
+      owner(anchor.web3.Keypair.generate().publicKey)
+    );
+    try {
+      await create(tooMany);
+      assert.fail("more than 10 owners were accepted");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "TooManyOwners");
+    }
+
+    try {
+      await create([owner(ownerB.publicKey), owner(ownerC.publicKey, 0)]);
+      assert.fail("an owner without weight was accepted");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "InvalidWeight");
+    }
+
+    try {
+      await create([owner(ownerB.publicKey, 1, ALL | 8)]);
+      assert.fail("unknown permission bits were accepted");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "InvalidPermissions");
+    }
+
+    // nobody could ever execute a proposal
+    try {
+      await create([
+        owner(ownerB.publicKey, 1, INITIATE | VOTE),
+        owner(ownerC.publicKey, 1, VOTE),
+      ]);
+      assert.fail("an owner set without an executor was accepted");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "InoperableOwnerSet");
+    }
+  });
+
+  it("Revoke, reject and cancel proposals!", async () => {
+    // owners are now the wallet, B, C and D with a threshold of 2
+    const approve = (proposalId: number, owner: anchor.web3.Keypair) =>
+      program.methods
+        .approveTransaction(new anchor.BN(proposalId))
+        .accounts({
+          multiSig: multiSigPDA,
+          proposal: proposalPDA(proposalId),
+          approver: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc();
+    const reject = (proposalId: number, owner: anchor.web3.Keypair) =>
+      program.methods
+        .rejectTransaction(new anchor.BN(proposalId))
+        .accounts({
+          multiSig: multiSigPDA,
+          proposal: proposalPDA(proposalId),
+          rejecter: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc();
+
+    for (const id of [3, 4]) {
+      await program.methods
+        .createTransactionProposal(encodeInstructions([]), null)
+        .accounts({
+          multiSig: multiSigPDA,
+          proposal: proposalPDA(id),
+          proposer: ownerB.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([ownerB])
+        .rpc();
+    }
+
+    // an approval can be taken back before execution
+    await approve(3, ownerC);
+    await program.methods
+      .revokeApproval(new anchor.BN(3))
+      .accounts({
+        multiSig: multiSigPDA,
+        proposal: proposalPDA(3),
+        approver: ownerC.publicKey,
+      })
+      .signers([ownerC])
+      .rpc();
+    let proposal = await program.account.transactionProposal.fetch(
+      proposalPDA(3)
+    );
+    assert.equal(proposal.approvers.length, 0);
+
+    // with 4 owners and a threshold of 2, the third rejection is final
+    await reject(3, ownerB);
+    await reject(3, ownerC);
+    proposal = await program.account.transactionProposal.fetch(proposalPDA(3));
+    assert.deepEqual(proposal.status, { active: {} });
+    await program.methods
+      .rejectTransaction(new anchor.BN(3))
+      .accounts({
+        multiSig: multiSigPDA,
+        proposal: proposalPDA(3),
+        rejecter: provider.wallet.publicKey,
+      })
+      .rpc();
+    proposal = await program.account.transactionProposal.fetch(proposalPDA(3));
+    assert.deepEqual(proposal.status, { rejected: {} });
+
+    try {
+      await program.methods
+        .cancelProposal(new anchor.BN(4))
+        .accounts({
+          multiSig: multiSigPDA,
+          proposal: proposalPDA(4),
+          proposer: ownerC.publicKey,
+        })
+        .signers([ownerC])
+        .rpc();
+      assert.fail("someone else cancelled the proposal");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "NotProposer");
+    }
+    await program.methods
+      .cancelProposal(new anchor.BN(4))
+      .accounts({
+        multiSig: multiSigPDA,
+        proposal: proposalPDA(4),
+        proposer: ownerB.publicKey,
+      })
+      .signers([ownerB])
+      .rpc();
+    proposal = await program.account.transactionProposal.fetch(proposalPDA(4));
+    assert.deepEqual(proposal.status, { cancelled: {} });
+  });
+
+  it("Pay out of the vault with a transfer proposal!", async () => {
+    const recipient = anchor.web3.Keypair.generate().publicKey;
+    const amount = new anchor.BN(anchor.web3.LAMPORTS_PER_SOL / 10);
+
+    // no mint means the transfer is in SOL
+    await program.methods
+      .proposeTransfer({ mint: null, amount, recipient }, null)
+      .accounts({
+        multiSig: multiSigPDA,
+        proposal: proposalPDA(5),
+        proposer: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    for (const approver of [ownerB, ownerC]) {
+      await program.methods
+        .approveTransaction(new anchor.BN(5))
+        .accounts({
+          multiSig: multiSigPDA,
+          proposal: proposalPDA(5),
+          approver: approver.publicKey,
+        })
+        .signers([approver])
+        .rpc();
+    }
+
+    // transfers do not run as raw instructions
+    try {
+      await program.methods
+        .executeTransaction(new anchor.BN(5))
+        .accounts({
+          multiSig: multiSigPDA,
+          proposal: proposalPDA(5),
+          vault: vaultPDA,
+          executor: provider.wallet.publicKey,
+        })
+        .rpc();
+      assert.fail("a transfer proposal was executed as instructions");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "WrongProposalKind");
+    }
+
+    await program.methods
+      .executeTransfer(new anchor.BN(5))
+      .accounts({
+        multiSig: multiSigPDA,
+        proposal: proposalPDA(5),
+        vault: vaultPDA,
+        recipient,
+        vaultTokenAccount: null,
+        recipientTokenAccount: null,
+        tokenProgram: null,
+        executor: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const proposal = await program.account.transactionProposal.fetch(
+      proposalPDA(5)
+    );
+    assert.deepEqual(proposal.status, { executed: {} });
+    assert.equal(
+      await provider.connection.getBalance(recipient),
+      amount.toNumber()
+    );
+  });
+
+  it("Spend within a spending limit without a proposal!", async () => {
+    const recipient = anchor.web3.Keypair.generate().publicKey;
+    const allowance = anchor.web3.LAMPORTS_PER_SOL / 20;
+    const spent = (allowance * 3) / 5;
+    const [limitPDA] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("spending-limit"),
+        multiSigPDA.toBuffer(),
+        new anchor.BN(0).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+
+    // ownerB may send up to the allowance per day, only to the recipient
+    const addLimit = await program.methods
+      .addSpendingLimit(
+        new anchor.BN(0),
+        null,
+        new anchor.BN(allowance),
+        new anchor.BN(24 * 60 * 60),
+        [ownerB.publicKey],
+        [recipient]
+      )
+      .accounts({
+        multiSig: multiSigPDA,
+        spendingLimit: limitPDA,
+        vault: vaultPDA,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .instruction();
+    await program.methods
+      .createTransactionProposal(encodeInstructions([addLimit]), null)
+      .accounts({
+        multiSig: multiSigPDA,
+        proposal: proposalPDA(6),
+        proposer: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    for (const approver of [ownerB, ownerC]) {
+      await program.methods
+        .approveTransaction(new anchor.BN(6))
+        .accounts({
+          multiSig: multiSigPDA,
+          proposal: proposalPDA(6),
+          approver: approver.publicKey,
+        })
+        .signers([approver])
+        .rpc();
+    }
+    await program.methods
+      .executeTransaction(new anchor.BN(6))
+      .accounts({
+        multiSig: multiSigPDA,
+        proposal: proposalPDA(6),
+        vault: vaultPDA,
+        executor: provider.wallet.publicKey,
+      })
+      .remainingAccounts([
+        { pubkey: program.programId, isSigner: false, isWritable: false },
+        { pubkey: multiSigPDA, isSigner: false, isWritable: false },
+        { pubkey: limitPDA, isSigner: false, isWritable: true },
+        { pubkey: vaultPDA, isSigner: false, isWritable: true },
+        {
+          pubkey: anchor.web3.SystemProgram.programId,
+          isSigner: false,
+          isWritable: false,
+        },
+      ])
+      .rpc();
+
+    const spend = (amount: number, to: anchor.web3.PublicKey) =>
+      program.methods
+        .spend(new anchor.BN(0), new anchor.BN(amount))
+        .accounts({
+          multiSig: multiSigPDA,
+          spendingLimit: limitPDA,
+          vault: vaultPDA,
+          recipient: to,
+          vaultTokenAccount: null,
+          recipientTokenAccount: null,
+          tokenProgram: null,
+          member: ownerB.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([ownerB])
+        .rpc();
+
+    await spend(spent, recipient);
+    assert.equal(
+      await provider.connection.getBalance(recipient),
+      spent
+    );
+
+    try {
+      await spend(spent, recipient);
+      assert.fail("the spending limit was exceeded");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "SpendingLimitExceeded");
+    }
+    try {
+      await spend(1, outsider.publicKey);
+      assert.fail("paid a destination outside the spending limit");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "DestinationNotAllowed");
+    }
+
+    const limit = await program.account.spendingLimit.fetch(limitPDA);
+    assert.equal(limit.remaining.toNumber(), allowance - spent);
+  });
+
+  it("Assemble a large proposal from buffer chunks!", async () => {
+    const [bufferPDA] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("proposal-buffer"),
+        multiSigPDA.toBuffer(),
+        provider.wallet.publicKey.toBuffer(),
+      ],
+      program.programId
+    );
+    const payload = encodeInstructions(
+      [1, 2, 3, 4].map(() =>
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: vaultPDA,
+          toPubkey: anchor.web3.Keypair.generate().publicKey,
+          lamports: 1,
+        })
+      )
+    );
+    const half = Math.floor(payload.length / 2);
+
+    const append = (offset: number, chunk: Buffer) =>
+      program.methods
+        .appendToBuffer(offset, chunk)
+        .accounts({
+          multiSig: multiSigPDA,
+          buffer: bufferPDA,
+          proposer: provider.wallet.publicKey,
+        })
+        .rpc();
+    const finalize = () =>
+      program.methods
+        .finalizeProposal(null)
+        .accounts({
+          multiSig: multiSigPDA,
+          buffer: bufferPDA,
+          proposal: proposalPDA(7),
+          proposer: provider.wallet.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+    await program.methods
+      .createProposalBuffer(
+        payload.length,
+        Array.from(createHash("sha256").update(payload).digest())
+      )
+      .accounts({
+        multiSig: multiSigPDA,
+        buffer: bufferPDA,
+        proposer: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    await append(0, payload.subarray(0, half));
+
+    // half a payload does not match the declared hash
+    try {
+      await finalize();
+      assert.fail("an incomplete buffer was finalized");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "BufferHashMismatch");
+    }
+    try {
+      await append(half + 1, payload.subarray(half));
+      assert.fail("a chunk was written past the end of the buffer");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "ChunkOutOfBounds");
+    }
+
+    await append(half, payload.subarray(half));
+    await finalize();
+
+    const proposal = await program.account.transactionProposal.fetch(
+      proposalPDA(7)
+    );
+    assert.ok(Buffer.from(proposal.instructions).equals(payload));
+    assert.isNull(await provider.connection.getAccountInfo(bufferPDA));
+  });
+
+  it("Approve with signatures collected offline!", async () => {
+    const instructions = encodeInstructions([]);
+    await program.methods
+      .createTransactionProposal(instructions, null)
+      .accounts({
+        multiSig: multiSigPDA,
+        proposal: proposalPDA(8),
+        proposer: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    // the hash covers the instructions and the (absent) transfer
+    const proposalHash = createHash("sha256")
+      .update(Buffer.concat([instructions, Buffer.from([0])]))
+      .digest();
+    const message = Buffer.concat([
+      Buffer.from("multi-sig approval"),
+      multiSigPDA.toBuffer(),
+      new anchor.BN(8).toArrayLike(Buffer, "le", 8),
+      new anchor.BN(0).toArrayLike(Buffer, "le", 4), // approval epoch
+      proposalHash,
+    ]);
+    const sign = (signer: anchor.web3.Keypair, signed: Buffer) =>
+      anchor.web3.Ed25519Program.createInstructionWithPrivateKey({
+        privateKey: signer.secretKey,
+        message: signed,
+      });
+    const approveWith = (signatures: anchor.web3.TransactionInstruction[]) =>
+      program.methods
+        .approveWithSignatures(new anchor.BN(8))
+        .accounts({
+          multiSig: multiSigPDA,
+          proposal: proposalPDA(8),
+          instructionsSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        })
+        .preInstructions(signatures)
+        .rpc();
+
+    // signatures from outsiders or over another message approve nothing
+    try {
+      await approveWith([
+        sign(outsider, message),
+        sign(ownerB, Buffer.from("something else")),
+      ]);
+      assert.fail("approved without a valid owner signature");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "NoValidSignatures");
+    }
+
+    // one relayed transaction records both owners, ignoring the outsider
+    await approveWith([
+      sign(ownerB, message),
+      sign(outsider, message),
+      sign(ownerC, message),
+    ]);
+    const proposal = await program.account.transactionProposal.fetch(
+      proposalPDA(8)
+    );
+    assert.deepEqual(
+      proposal.approvers.map((key) => key.toBase58()),
+      [ownerB.publicKey.toBase58(), ownerC.publicKey.toBase58()]
+    );
+    assert.equal(proposal.ready, true);
+
+    // a revoked approval cannot be restored by relaying its old signature
+    await program.methods
+      .revokeApproval(new anchor.BN(8))
+      .accounts({
+        multiSig: multiSigPDA,
+        proposal: proposalPDA(8),
+        approver: ownerB.publicKey,
+      })
+      .signers([ownerB])
+      .rpc();
+    try {
+      await approveWith([sign(ownerB, message)]);
+      assert.fail("a revoked approval was replayed");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "NoValidSignatures");
+    }
+    const revoked = await program.account.transactionProposal.fetch(
+      proposalPDA(8)
+    );
+    assert.equal(revoked.approvalEpoch, 1);
+    assert.deepEqual(
+      revoked.approvers.map((key) => key.toBase58()),
+      [ownerC.publicKey.toBase58()]
+    );
+  });
+
+  it("Enforce timelocks and expiry!", async () => {
+    // a single-owner multisig with a one hour timelock
+    const [lockedPDA] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("multi-sig"),
+        provider.wallet.publicKey.toBuffer(),
+        Buffer.from("timelocked"),
+        Buffer.from([nonce]),
+      ],
+      program.programId
+    );
+    const [lockedProposalPDA] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("proposal"),
+        lockedPDA.toBuffer(),
+        new anchor.BN(0).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    const createLocked = (timelockSeconds: anchor.BN) =>
+      program.methods
+        .createMultiSig(
+          "timelocked",
+          nonce,
+          [{ key: provider.wallet.publicKey, weight: 1, permissions: ALL }],
+          1,
+          timelockSeconds
+        )
+        .accounts({
+          multiSig: lockedPDA,
+          user: provider.wallet.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+    // timelocks are capped at a year, so adding them to a timestamp cannot overflow
+    try {
+      await createLocked(new anchor.BN(366 * 24 * 60 * 60));
+      assert.fail("a timelock over a year was accepted");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "InvalidTimelock");
+    }
+    await createLocked(new anchor.BN(3600));
+
+    // expiring before the timelock could pass is pointless
+    const now = Math.floor(Date.now() / 1000);
+    try {
+      await program.methods
+        .createTransactionProposal(encodeInstructions([]), new anchor.BN(now + 60))
+        .accounts({
+          multiSig: lockedPDA,
+          proposal: lockedProposalPDA,
+          proposer: provider.wallet.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+      assert.fail("a proposal expiring within the timelock was accepted");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "InvalidExpiry");
+    }
+
+    await program.methods
+      .createTransactionProposal(encodeInstructions([]), new anchor.BN(now + 7200))
+      .accounts({
+        multiSig: lockedPDA,
+        proposal: lockedProposalPDA,
+        proposer: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    await program.methods
+      .approveTransaction(new anchor.BN(0))
+      .accounts({
+        multiSig: lockedPDA,
+        proposal: lockedProposalPDA,
+        approver: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    const proposal = await program.account.transactionProposal.fetch(
+      lockedProposalPDA
+    );
+    assert.isNotNull(proposal.approvedAt);
+    assert.equal(proposal.expiresAt.toNumber(), now + 7200);
+
+    try {
+      await program.methods
+        .executeTransaction(new anchor.BN(0))
+        .accounts({
+          multiSig: lockedPDA,
+          proposal: lockedProposalPDA,
+          executor: provider.wallet.publicKey,
+        })
+        .rpc();
+      assert.fail("a proposal ran within its timelock");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "TimelockNotElapsed");
+    }
+  });
+
+  it("Tally approvals by owner weight!", async () => {
+    // a founder with weight 3 and two advisors with weight 1, threshold 3
+    const [weightedPDA] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("multi-sig"),
+        provider.wallet.publicKey.toBuffer(),
+        Buffer.from("weighted"),
+        Buffer.from([nonce]),
+      ],
+      program.programId
+    );
+    const weightedProposalPDA = (proposalId: number) =>
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("proposal"),
+          weightedPDA.toBuffer(),
+          new anchor.BN(proposalId).toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      )[0];
+    await program.methods
+      .createMultiSig(
+        "weighted",
+        nonce,
+        [
+          { key: provider.wallet.publicKey, weight: 3, permissions: ALL },
+          { key: ownerB.publicKey, weight: 1, permissions: ALL },
+          { key: ownerC.publicKey, weight: 1, permissions: ALL },
+        ],
+        3,
+        new anchor.BN(0)
+      )
+      .accounts({
+        multiSig: weightedPDA,
+        user: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    for (const id of [0, 1]) {
+      await program.methods
+        .createTransactionProposal(encodeInstructions([]), null)
+        .accounts({
+          multiSig: weightedPDA,
+          proposal: weightedProposalPDA(id),
+          proposer: provider.wallet.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+    }
+
+    // the founder alone carries the threshold
+    await program.methods
+      .approveTransaction(new anchor.BN(0))
+      .accounts({
+        multiSig: weightedPDA,
+        proposal: weightedProposalPDA(0),
+        approver: provider.wallet.publicKey,
+      })
+      .rpc();
+    let proposal = await program.account.transactionProposal.fetch(
+      weightedProposalPDA(0)
+    );
+    assert.equal(proposal.ready, true);
+
+    // both advisors together do not
+    for (const advisor of [ownerB, ownerC]) {
+      await program.methods
+        .approveTransaction(new anchor.BN(1))
+        .accounts({
+          multiSig: weightedPDA,
+          proposal: weightedProposalPDA(1),
+          approver: advisor.publicKey,
+        })
+        .signers([advisor])
+        .rpc();
+    }
+    proposal = await program.account.transactionProposal.fetch(
+      weightedProposalPDA(1)
+    );
+    assert.equal(proposal.ready, false);
+  });
+
+  it("Check owner permissions!", async () => {
+    // the wallet proposes and votes, ownerB is a vote-only cold key and
+    // ownerC an execute-only bot
+    const [rolesPDA] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("multi-sig"),
+        provider.wallet.publicKey.toBuffer(),
+        Buffer.from("roles"),
+        Buffer.from([nonce]),
+      ],
+      program.programId
+    );
+    const [rolesProposalPDA] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("proposal"),
+        rolesPDA.toBuffer(),
+        new anchor.BN(0).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    const [rolesVaultPDA] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), rolesPDA.toBuffer()],
+      program.programId
+    );
+    const owners = [
+      {
+        key: provider.wallet.publicKey,
+        weight: 1,
+        permissions: INITIATE | VOTE,
+      },
+      { key: ownerB.publicKey, weight: 1, permissions: VOTE },
+      { key: ownerC.publicKey, weight: 1, permissions: EXECUTE },
+    ];
+    const create = (threshold: number) =>
+      program.methods
+        .createMultiSig("roles", nonce, owners, threshold, new anchor.BN(0))
+        .accounts({
+          multiSig: rolesPDA,
+          user: provider.wallet.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+    // the bot's weight does not count towards the threshold
+    try {
+      await create(3);
+      assert.fail("the threshold counted an owner who cannot vote");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "InvalidThreshold");
+    }
+    await create(2);
+
+    const propose = (proposer: anchor.web3.Keypair) =>
+      program.methods
+        .createTransactionProposal(encodeInstructions([]), null)
+        .accounts({
+          multiSig: rolesPDA,
+          proposal: rolesProposalPDA,
+          proposer: proposer.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([proposer])
+        .rpc();
+    const approve = (approver: anchor.web3.Keypair) =>
+      program.methods
+        .approveTransaction(new anchor.BN(0))
+        .accounts({
+          multiSig: rolesPDA,
+          proposal: rolesProposalPDA,
+          approver: approver.publicKey,
+        })
+        .signers([approver])
+        .rpc();
+    const execute = (executor: anchor.web3.Keypair) =>
+      program.methods
+        .executeTransaction(new anchor.BN(0))
+        .accounts({
+          multiSig: rolesPDA,
+          proposal: rolesProposalPDA,
+          vault: rolesVaultPDA,
+          executor: executor.publicKey,
+        })
+        .signers([executor])
+        .rpc();
+    const wallet = (provider.wallet as anchor.Wallet).payer;
+
+    try {
+      await propose(ownerB);
+      assert.fail("a vote-only owner created a proposal");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "MissingPermission");
+    }
+    await propose(wallet);
+
+    try {
+      await approve(ownerC);
+      assert.fail("an execute-only owner voted");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "MissingPermission");
+    }
+    await approve(wallet);
+    await approve(ownerB);
+
+    try {
+      await execute(wallet);
+      assert.fail("an owner without Execute executed the proposal");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "MissingPermission");
+    }
+    await execute(ownerC);
+
+    const proposal = await program.account.transactionProposal.fetch(
+      rolesProposalPDA
+    );
+    assert.deepEqual(proposal.status, { executed: {} });
+  });
+
+  it("Close finished proposals and refund their rent!", async () => {
+    // proposal 8 is still pending
+    try {
+      await program.methods
+        .closeProposal(new anchor.BN(8))
+        .accounts({
+          multiSig: multiSigPDA,
+          proposal: proposalPDA(8),
+          proposer: provider.wallet.publicKey,
+        })
+        .rpc();
+      assert.fail("an active proposal was closed");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "ProposalStillActive");
+    }
+
+    // executed 1, rejected 3 and cancelled 4 in one go, each refunding its
+    // own proposer
+    const balance = await provider.connection.getBalance(ownerB.publicKey);
+    const rent = (await provider.connection.getAccountInfo(proposalPDA(3)))
+      .lamports;
+    await program.methods
+      .closeProposals()
+      .accounts({ multiSig: multiSigPDA })
+      .remainingAccounts(
+        (
+          [
+            [1, provider.wallet.publicKey],
+            [3, ownerB.publicKey],
+            [4, ownerB.publicKey],
+          ] as [number, anchor.web3.PublicKey][]
+        ).flatMap(([id, proposer]) => [
+          { pubkey: proposalPDA(id), isSigner: false, isWritable: true },
+          { pubkey: proposer, isSigner: false, isWritable: true },
+        ])
+      )
+      .rpc();
+
+    for (const id of [1, 3, 4]) {
+      assert.isNull(await provider.connection.getAccountInfo(proposalPDA(id)));
+    }
+    assert.equal(
+      await provider.connection.getBalance(ownerB.publicKey),
+      balance + 2 * rent
+    );
+  });
+
+  it("Pause and veto as a guardian!", async () => {
+    // the wallet alone owns the multisig and ownerC guards it, vetoes are
+    // only possible during the short timelock
+    const [guardedPDA] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("multi-sig"),
+        provider.wallet.publicKey.toBuffer(),
+        Buffer.from("guarded"),
+        Buffer.from([nonce]),
+      ],
+      program.programId
+    );
+    const guardedProposalPDA = (proposalId: number) =>
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("proposal"),
+          guardedPDA.toBuffer(),
+          new anchor.BN(proposalId).toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      )[0];
+    const [guardedVaultPDA] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), guardedPDA.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .createMultiSig(
+        "guarded",
+        nonce,
+        [{ key: provider.wallet.publicKey, weight: 1, permissions: ALL }],
+        1,
+        new anchor.BN(2)
+      )
+      .accounts({
+        multiSig: guardedPDA,
+        user: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const propose = (id: number, instructions: Buffer) =>
+      program.methods
+        .createTransactionProposal(instructions, null)
+        .accounts({
+          multiSig: guardedPDA,
+          proposal: guardedProposalPDA(id),
+          proposer: provider.wallet.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+    const approve = (id: number) =>
+      program.methods
+        .approveTransaction(new anchor.BN(id))
+        .accounts({
+          multiSig: guardedPDA,
+          proposal: guardedProposalPDA(id),
+          approver: provider.wallet.publicKey,
+        })
+        .rpc();
+    // executes a proposal made of one instruction on the multisig itself
+    const execute = (id: number) =>
+      program.methods
+        .executeTransaction(new anchor.BN(id))
+        .accounts({
+          multiSig: guardedPDA,
+          proposal: guardedProposalPDA(id),
+          vault: guardedVaultPDA,
+          executor: provider.wallet.publicKey,
+        })
+        .remainingAccounts([
+          { pubkey: program.programId, isSigner: false, isWritable: false },
+          { pubkey: guardedPDA, isSigner: false, isWritable: true },
+          { pubkey: guardedVaultPDA, isSigner: false, isWritable: false },
+        ])
+        .rpc();
+    const pause = (guardian: anchor.web3.Keypair) =>
+      program.methods
+        .pause()
+        .accounts({ multiSig: guardedPDA, guardian: guardian.publicKey })
+        .signers([guardian])
+        .rpc();
+
+    const veto = (id: number) =>
+      program.methods
+        .veto(new anchor.BN(id))
+        .accounts({
+          multiSig: guardedPDA,
+          proposal: guardedProposalPDA(id),
+          guardian: ownerC.publicKey,
+        })
+        .signers([ownerC])
+        .rpc();
+    const waitOutTimelock = () =>
+      new Promise((resolve) => setTimeout(resolve, 3000));
+
+    // guardians are set through a proposal
+    const setGuardians = await program.methods
+      .setGuardians([ownerC.publicKey])
+      .accounts({ multiSig: guardedPDA, vault: guardedVaultPDA })
+      .instruction();
+    await propose(0, encodeInstructions([setGuardians]));
+    await approve(0);
+    await waitOutTimelock();
+    await execute(0);
+
+    // a guardian can veto a proposal while its timelock runs, not before
+    await propose(1, encodeInstructions([]));
+    try {
+      await veto(1);
+      assert.fail("a proposal was vetoed before its timelock started");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "NotInTimelock");
+    }
+    await approve(1);
+    await veto(1);
+    try {
+      await execute(1);
+      assert.fail("a vetoed proposal was executed");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "ProposalVetoed");
+    }
+
+    // only guardians can pause, and a paused multisig takes no new business
+    try {
+      await pause(ownerB);
+      assert.fail("someone other than a guardian paused the multisig");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "NotAGuardian");
+    }
+    await pause(ownerC);
+    try {
+      await propose(2, encodeInstructions([]));
+      assert.fail("a paused multisig accepted a proposal");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "MultiSigPaused");
+    }
+
+    // unpausing takes a proposal approved by the owners, which the guardian
+    // that paused the multisig cannot veto
+    const unpause = await program.methods
+      .unpause()
+      .accounts({ multiSig: guardedPDA, vault: guardedVaultPDA })
+      .instruction();
+    await propose(2, encodeInstructions([unpause]));
+    await approve(2);
+    try {
+      await veto(2);
+      assert.fail("a guardian vetoed the unpause");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "RecoveryProposal");
+    }
+    await waitOutTimelock();
+    await execute(2);
+
+    const multiSigAccount = await program.account.multiSig.fetch(guardedPDA);
+    assert.equal(multiSigAccount.paused, false);
+    assert.ok(multiSigAccount.guardians[0].equals(ownerC.publicKey));
+  });
 });